@@ -1,4 +1,9 @@
-use clap::{Parser, Subcommand};
+use std::{path::PathBuf, str::FromStr};
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::language::Language;
+use crate::listing::{GroupBy, SortKey};
 
 const AFTER_HELP: &str = "\
 Note format:
@@ -16,6 +21,10 @@ Note format:
   Pending:
     <!-- TODO rewrite the prologue #draft -->
 
+  Finished (hidden from errata and pending unless --all):
+    <!-- TODO [x] rewrite the prologue -->   <!-- DONE rewrite the prologue -->
+    <!-- FIXME WONTFIX timeline in ch. 4 --> <!-- WONTFIX timeline in ch. 4 -->
+
   Trailing #tags at the end of a note are hidden in display but still
   searchable. In-text tags (e.g. the #character arc) stay in the text.
 
@@ -39,6 +48,61 @@ files are rescanned on each run. Full-text search loads only for the TUI or sear
 pub struct Args {
     #[command(subcommand)]
     pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub output: Output,
+}
+
+/// Options shared by the commands that pretty-print notes.
+#[derive(Debug, clap::Args)]
+pub struct Output {
+    /// Wrap note bodies without hyphenating long words
+    #[arg(long, global = true)]
+    pub no_hyphenate: bool,
+
+    /// Wrap at COLS columns, or `auto` for the full terminal width
+    /// [default: terminal width, at most 80]
+    #[arg(long, global = true, value_name = "COLS")]
+    pub width: Option<Width>,
+
+    /// Print each note body on a single line
+    #[arg(long, global = true, conflicts_with = "width")]
+    pub no_wrap: bool,
+
+    /// Colorize output. `auto` colors only a terminal and honors NO_COLOR
+    #[arg(long, global = true, value_name = "WHEN", default_value = "auto")]
+    pub color: ColorChoice,
+
+    /// Print straight to stdout instead of paging long output
+    #[arg(long, global = true)]
+    pub no_pager: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Width {
+    Auto,
+    Columns(usize),
+}
+
+impl FromStr for Width {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(Width::Auto);
+        }
+        match s.parse() {
+            Ok(0) | Err(_) => Err(format!("expected a column count or `auto`, got {s:?}")),
+            Ok(n) => Ok(Width::Columns(n)),
+        }
+    }
 }
 
 impl Args {
@@ -55,26 +119,193 @@ pub enum Command {
     Define(Define),
     /// Search notes by tag (default) or full text (-f)
     Search(Search),
-    /// List all FIXME (errata) notes
-    Errata,
-    /// List all TODO (pending) notes
-    Pending,
+    /// List open FIXME (errata) notes
+    Errata(Worklist),
+    /// List open TODO (pending) notes
+    Pending(Worklist),
     /// Pretty-print the full glossary of definitions
     Glossary,
     /// Pretty-print every note (notes, definitions, FIXMEs, and TODOs)
-    All,
+    All(Listing),
+    /// Summarize the notes: counts, density, tags, and per-file errata
+    Stats(StatsArgs),
+    /// Compare notes between two git revisions or two directories
+    Diff(DiffArgs),
+    /// Record today's FIXME, TODO, and tag counts to the progress history
+    Snapshot,
+    /// Chart recorded snapshots as sparklines
+    Progress(ProgressArgs),
+    /// Print the notes of a view saved in the TUI, or list saved views
+    View(ViewArgs),
     /// Open the interactive tag browser (default when no command is given)
-    Tui,
+    Tui(Scope),
 }
 
 #[derive(Debug, Parser)]
 #[command(
-    after_help = "Example:\n  notes config \"**/*.md\"\n  notes config \"src/chapter.*.md\""
+    after_help = "Example:\n  notes config \"**/*.md\"\n  notes config \"src/chapter.*.md\"\n  \
+notes config --language de --file-language \"fr/**=fr\" --fold-accents true\n  \
+notes config --pager false"
 )]
 pub struct Config {
     /// Glob of files to scan. Omit to show current config.
     /// Quote globs in the shell. Surrounding quotes in the value are stripped.
     pub glob: Option<String>,
+
+    /// Default manuscript language for full-text search (en, de, fr, es, it, nl, pt)
+    #[arg(long, value_name = "LANG")]
+    pub language: Option<Language>,
+
+    /// Language for files matching GLOB; repeatable. An empty LANG removes the entry.
+    #[arg(long = "file-language", value_name = "GLOB=LANG")]
+    pub file_languages: Vec<String>,
+
+    /// Strip accents and diacritics before full-text matching
+    #[arg(long, value_name = "BOOL")]
+    pub fold_accents: Option<bool>,
+
+    /// Page output taller than the terminal through $PAGER (default: true)
+    #[arg(long, value_name = "BOOL")]
+    pub pager: Option<bool>,
+}
+
+/// Ordering and sectioning for `errata`, `pending`, and `all`.
+#[derive(Debug, clap::Args)]
+#[command(after_help = "Examples:\n  notes errata chapters/ch04.md\n  \
+notes errata --group-by file\n  notes all --sort mtime --reverse --limit 20\n  \
+notes errata --since 2026-09-01 --author ada\n  notes pending --new-since v2-draft")]
+pub struct Listing {
+    #[command(flatten)]
+    pub scope: Scope,
+
+    /// Sort notes instead of listing them in scan order
+    #[arg(long, value_name = "KEY")]
+    pub sort: Option<SortKey>,
+
+    /// Print notes in sections under a header per file, tag, or kind
+    #[arg(long, value_name = "BY")]
+    pub group_by: Option<GroupBy>,
+
+    /// Reverse the listing order
+    #[arg(long)]
+    pub reverse: bool,
+
+    /// Print at most N notes
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+
+    /// Only notes whose line was last changed on or after DATE (git blame)
+    #[arg(long, value_name = "YYYY-MM-DD", value_parser = crate::date::parse_date)]
+    pub since: Option<i64>,
+
+    /// Only notes whose line was last changed by AUTHOR (name or email, substring)
+    #[arg(long, value_name = "AUTHOR")]
+    pub author: Option<String>,
+
+    /// Only notes added or changed after REV (a tag, branch, or commit)
+    #[arg(long, value_name = "REV")]
+    pub new_since: Option<String>,
+}
+
+/// `errata` and `pending`: a listing that skips finished items.
+#[derive(Debug, Parser)]
+pub struct Worklist {
+    #[command(flatten)]
+    pub listing: Listing,
+
+    /// Also list items marked done ([x], DONE) or won't fix (WONTFIX)
+    #[arg(long)]
+    pub all: bool,
+}
+
+/// Files or directories to scan instead of everything the glob matches.
+#[derive(Debug, Default, clap::Args)]
+pub struct Scope {
+    /// Only scan these files or directories (within the configured glob)
+    #[arg(value_name = "PATH")]
+    pub paths: Vec<PathBuf>,
+
+    /// Same as a positional PATH; repeatable
+    #[arg(long = "path", value_name = "PATH")]
+    pub more_paths: Vec<PathBuf>,
+}
+
+impl Scope {
+    pub fn paths(&self) -> impl Iterator<Item = &PathBuf> {
+        self.paths.iter().chain(&self.more_paths)
+    }
+}
+
+#[derive(Debug, Parser)]
+#[command(
+    after_help = "Examples:\n  notes stats\n  notes stats --format json > stats.json\n  \
+notes stats chapters/"
+)]
+pub struct StatsArgs {
+    #[command(flatten)]
+    pub scope: Scope,
+
+    /// Output format
+    #[arg(long, value_name = "FORMAT", default_value = "text")]
+    pub format: OutputFormat,
+
+    /// How many of the most used tags to list
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    pub top: usize,
+}
+
+#[derive(Debug, Parser)]
+#[command(after_help = "\
+Each side is a directory or a git revision; both are read with the configured
+glob. Notes with identical text are matched first (a different file counts as
+moved), then edited notes in the same file (changed). The rest are added or
+removed.
+
+Examples:
+  notes diff draft-2 draft-3
+  notes diff HEAD~5 HEAD --format json
+  notes diff ../book-v2 .")]
+pub struct DiffArgs {
+    /// Old side: a directory or git revision
+    pub old: String,
+
+    /// New side: a directory or git revision
+    pub new: String,
+
+    /// Output format
+    #[arg(long, value_name = "FORMAT", default_value = "text")]
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Parser)]
+#[command(after_help = "Record with `notes snapshot` (e.g. daily, from cron or a git hook).")]
+pub struct ProgressArgs {
+    /// Output format (`json` prints the raw snapshots)
+    #[arg(long, value_name = "FORMAT", default_value = "text")]
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Parser)]
+#[command(
+    after_help = "Save views in the TUI with `V`.\n\nExamples:\n  notes view\n  \
+notes view army --group-by file\n  notes view army --delete"
+)]
+pub struct ViewArgs {
+    /// Saved view to print; omit to list this project's views
+    pub name: Option<String>,
+
+    /// Delete the view instead of printing it
+    #[arg(long)]
+    pub delete: bool,
+
+    #[command(flatten)]
+    pub listing: Listing,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
 }
 
 #[derive(Debug, Parser)]
@@ -84,13 +315,28 @@ pub struct Define {
 }
 
 #[derive(Debug, Parser)]
-#[command(
-    after_help = "Examples:\n  notes search character\n  notes search -f \"tax harvested\""
-)]
+#[command(after_help = "\
+Full-text query syntax (-f):
+  words            every word must match
+  \"a phrase\"       exact phrase
+  -word            exclude (also -\"phrase\", -tag:x)
+  a OR b           either side
+  term:x tag:x     definitions of x / notes tagged x
+  kind:fixme       note, define, fixme, or todo
+  path:ch04        file path contains ch04
+
+Examples:
+  notes search character
+  notes search -f \"tax harvested\"
+  notes search -f 'tax -harvested kind:fixme'
+  notes search -f tax chapters/ch04.md")]
 pub struct Search {
     /// Tag name, or full-text query when -f is set
     pub query: String,
 
+    #[command(flatten)]
+    pub scope: Scope,
+
     /// Full-text search over note bodies (builds the in-memory indexer)
     #[arg(short = 'f', long = "full-text")]
     pub full_text: bool,
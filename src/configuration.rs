@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     env, io,
     path::{Path, PathBuf},
 };
@@ -8,6 +8,7 @@ use abseil::Provider;
 use serde::{Deserialize, Serialize};
 
 use crate::error::Error;
+use crate::language::Language;
 
 const APP_NAME: &str = "notes";
 
@@ -21,19 +22,48 @@ pub struct AppState {
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DirConfig {
     pub glob: String,
+    /// Default manuscript language for full-text analysis.
+    #[serde(default)]
+    pub language: Language,
+    /// File glob → language, for files that differ from the default.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub languages: BTreeMap<String, Language>,
+    /// Strip diacritics before indexing and matching.
+    #[serde(default)]
+    pub fold_accents: bool,
+    /// Page long CLI output through `$PAGER`.
+    #[serde(default = "default_pager")]
+    pub pager: bool,
 }
 
-fn provider() -> crate::Result<Provider> {
+fn default_pager() -> bool {
+    true
+}
+
+impl DirConfig {
+    pub fn new(glob: impl Into<String>) -> Self {
+        Self {
+            glob: glob.into(),
+            language: Language::default(),
+            languages: BTreeMap::new(),
+            fold_accents: false,
+            pager: default_pager(),
+        }
+    }
+}
+
+/// Storage for `filename` in the app's config dir.
+pub fn provider(filename: &str) -> crate::Result<Provider> {
     Ok(Provider::builder(APP_NAME)
         .pretty()
         .use_config_dir()
-        .with_filename("config.json")
+        .with_filename(filename)
         .build()?)
 }
 
 impl AppState {
     pub fn load() -> crate::Result<Self> {
-        let provider = provider()?;
+        let provider = provider("config.json")?;
         match provider.load::<AppState>() {
             Ok(state) => Ok(state),
             Err(abseil::Error::NotFound) => Ok(Self::default()),
@@ -42,7 +72,7 @@ impl AppState {
     }
 
     pub fn save(&self) -> crate::Result<()> {
-        provider()?.store(self)?;
+        provider("config.json")?.store(self)?;
         Ok(())
     }
 
@@ -59,6 +89,11 @@ fn key(dir: &Path) -> String {
     dir.to_string_lossy().into_owned()
 }
 
+/// Key for per-project state kept beside the config (canonical cwd).
+pub fn project_key() -> crate::Result<String> {
+    Ok(key(&current_dir()?))
+}
+
 pub fn current_dir() -> crate::Result<PathBuf> {
     let dir = env::current_dir()?;
     Ok(dir.canonicalize()?)
@@ -74,7 +109,7 @@ pub fn load_or_prompt() -> crate::Result<DirConfig> {
     }
 
     let glob = prompt_glob(&dir)?;
-    let config = DirConfig { glob };
+    let config = DirConfig::new(glob);
     state.set(&dir, config.clone());
     state.save()?;
     Ok(config)
@@ -83,12 +118,29 @@ pub fn load_or_prompt() -> crate::Result<DirConfig> {
 pub fn set_glob(glob: impl AsRef<str>) -> crate::Result<DirConfig> {
     let dir = current_dir()?;
     let mut state = AppState::load()?;
-    let config = DirConfig {
-        glob: normalize_glob(glob.as_ref()),
-    };
-    if config.glob.is_empty() {
+    let glob = normalize_glob(glob.as_ref());
+    if glob.is_empty() {
         return Err(Error::Config("glob must not be empty".into()));
     }
+    let config = match state.get(&dir).cloned() {
+        Some(config) => DirConfig { glob, ..config },
+        None => DirConfig::new(glob),
+    };
+    state.set(&dir, config.clone());
+    state.save()?;
+    Ok(config)
+}
+
+/// Modify the current directory's existing config in place.
+pub fn update(f: impl FnOnce(&mut DirConfig)) -> crate::Result<DirConfig> {
+    let dir = current_dir()?;
+    let mut state = AppState::load()?;
+    let Some(mut config) = state.get(&dir).cloned() else {
+        return Err(Error::Config(
+            "no config for this directory; run `notes config <glob>` first".into(),
+        ));
+    };
+    f(&mut config);
     state.set(&dir, config.clone());
     state.save()?;
     Ok(config)
@@ -119,6 +171,24 @@ fn prompt_glob(dir: &Path) -> crate::Result<String> {
     Ok(glob)
 }
 
+/// Parse `GLOB=LANG` from `notes config --file-language`. An empty `LANG`
+/// yields `None`, meaning "remove the override".
+pub fn parse_file_language(raw: &str) -> crate::Result<(String, Option<Language>)> {
+    let Some((glob, lang)) = raw.rsplit_once('=') else {
+        return Err(Error::Config(format!("expected GLOB=LANG, got {raw:?}")));
+    };
+    let glob = normalize_glob(glob);
+    if glob.is_empty() {
+        return Err(Error::Config("glob must not be empty".into()));
+    }
+    glob::Pattern::new(&glob)?;
+    let lang = match lang.trim() {
+        "" => None,
+        lang => Some(lang.parse().map_err(Error::Config)?),
+    };
+    Ok((glob, lang))
+}
+
 /// Strip surrounding quotes users may paste from shell examples.
 fn normalize_glob(raw: &str) -> String {
     let s = raw.trim();
@@ -134,7 +204,8 @@ fn normalize_glob(raw: &str) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::normalize_glob;
+    use super::{normalize_glob, parse_file_language, DirConfig};
+    use crate::language::Language;
 
     #[test]
     fn strips_double_quotes() {
@@ -150,4 +221,20 @@ mod tests {
     fn leaves_bare_glob() {
         assert_eq!(normalize_glob("**/*.md"), "**/*.md");
     }
+
+    #[test]
+    fn parses_file_language() {
+        let (glob, lang) = parse_file_language("'fr/**/*.md'=fr").unwrap();
+        assert_eq!(glob, "fr/**/*.md");
+        assert_eq!(lang, Some(Language::French));
+        assert_eq!(parse_file_language("de/*=").unwrap().1, None);
+        assert!(parse_file_language("de/*").is_err());
+        assert!(parse_file_language("de/*=klingon").is_err());
+    }
+
+    #[test]
+    fn old_configs_page_by_default() {
+        let config: DirConfig = serde_json::from_str(r#"{ "glob": "**/*.md" }"#).unwrap();
+        assert!(config.pager);
+    }
 }
@@ -0,0 +1,77 @@
+use std::{fs, ops::RangeInclusive};
+
+use crate::note::{Note, Parser};
+
+/// A note's source file, for the TUI source view.
+pub struct SourceContext {
+    pub lines: Vec<String>,
+    /// 1-based lines the note's comment spans.
+    pub comment: RangeInclusive<usize>,
+}
+
+impl SourceContext {
+    pub fn load(note: &Note) -> crate::Result<Self> {
+        let source = fs::read_to_string(&note.path)?;
+        Ok(Self::new(&source, note))
+    }
+
+    fn new(source: &str, note: &Note) -> Self {
+        // A comment that no longer matches (file edited since the scan)
+        // marks only its first line.
+        let end = match Parser::new().locate(source, note) {
+            Some(body) => {
+                note.line + source[..body.end].matches('\n').count()
+                    - source[..body.start].matches('\n').count()
+            }
+            None => note.line,
+        };
+        Self {
+            lines: source.lines().map(str::to_owned).collect(),
+            comment: note.line..=end,
+        }
+    }
+
+    /// Index of the first line to show in `height` rows: the comment a third
+    /// of the way down, moved by `scroll`, and kept inside the file.
+    pub fn first_line(&self, height: usize, scroll: isize) -> usize {
+        self.start(height)
+            .saturating_add_signed(self.clamp_scroll(height, scroll))
+    }
+
+    /// `scroll` limited to the offsets that still move [`first_line`], so
+    /// scrolling past either end is not remembered.
+    ///
+    /// [`first_line`]: SourceContext::first_line
+    pub fn clamp_scroll(&self, height: usize, scroll: isize) -> isize {
+        let start = self.start(height) as isize;
+        let last = self.lines.len().saturating_sub(height) as isize;
+        scroll.clamp(-start, last - start)
+    }
+
+    fn start(&self, height: usize) -> usize {
+        (*self.comment.start() - 1).saturating_sub(height / 3)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn spans_a_multi_line_comment() {
+        let source = "one\ntwo <!-- TODO a\nlong one -->\nthree\nfour\nfive\n";
+        let note = Parser::new().parse_file(Path::new("t.md"), source)[0].clone();
+        let context = SourceContext::new(source, &note);
+        assert_eq!(context.comment, 2..=3);
+        assert_eq!(context.lines.len(), 6);
+
+        assert_eq!(context.first_line(3, 0), 0);
+        assert_eq!(context.first_line(3, 2), 2);
+        assert_eq!(context.first_line(3, 10), 3);
+        assert_eq!(context.first_line(3, -5), 0);
+        assert_eq!(context.clamp_scroll(3, 10), 3);
+        assert_eq!(context.clamp_scroll(3, -5), 0);
+        assert_eq!(context.clamp_scroll(10, 4), 0);
+    }
+}
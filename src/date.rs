@@ -0,0 +1,111 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Parse `YYYY-MM-DD` as midnight UTC, in seconds since the Unix epoch.
+pub fn parse_date(s: &str) -> Result<i64, String> {
+    let bad = || format!("expected a date like 2026-09-01, got {s:?}");
+    let mut parts = s.trim().splitn(3, '-');
+    let mut next =
+        || -> Result<i64, String> { parts.next().and_then(|p| p.parse().ok()).ok_or_else(bad) };
+    let (y, m, d) = (next()?, next()?, next()?);
+    if !(1..=12).contains(&m) || !(1..=days_in_month(y, m)).contains(&d) {
+        return Err(bad());
+    }
+    Ok(days_from_civil(y, m, d) * 86_400)
+}
+
+fn days_in_month(y: i64, m: i64) -> i64 {
+    match m {
+        2 if y % 4 == 0 && (y % 100 != 0 || y % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// `YYYY-MM-DD` (UTC) for seconds since the Unix epoch.
+pub fn format_date(time: i64) -> String {
+    let (y, m, d) = civil_from_days(time.div_euclid(86_400));
+    format!("{y:04}-{m:02}-{d:02}")
+}
+
+/// Days since 1970-01-01 in the proleptic Gregorian calendar.
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (m + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Inverse of [`days_from_civil`].
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    (y, m, d)
+}
+
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Rough age of `time` relative to `now`, e.g. "3 days ago".
+pub fn age(time: i64, now: i64) -> String {
+    let secs = (now - time).max(0);
+    let (n, unit) = match secs {
+        0..60 => return "just now".into(),
+        60..3_600 => (secs / 60, "minute"),
+        3_600..86_400 => (secs / 3_600, "hour"),
+        86_400..2_592_000 => (secs / 86_400, "day"),
+        2_592_000..31_536_000 => (secs / 2_592_000, "month"),
+        _ => (secs / 31_536_000, "year"),
+    };
+    let s = if n == 1 { "" } else { "s" };
+    format!("{n} {unit}{s} ago")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dates_as_utc_midnight() {
+        assert_eq!(parse_date("1970-01-01"), Ok(0));
+        assert_eq!(parse_date("2026-09-01"), Ok(1_788_220_800));
+        assert!(parse_date("2026-13-01").is_err());
+        assert!(parse_date("2026-02-31").is_err());
+        assert!(parse_date("2026-04-31").is_err());
+        assert!(parse_date("2026-02-29").is_err());
+        assert!(parse_date("2024-02-29").is_ok());
+        assert!(parse_date("1900-02-29").is_err());
+        assert!(parse_date("yesterday").is_err());
+    }
+
+    #[test]
+    fn formats_dates_round_trip() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(
+            format_date(parse_date("2026-09-01").unwrap() + 3_600),
+            "2026-09-01"
+        );
+        assert_eq!(format_date(parse_date("2000-02-29").unwrap()), "2000-02-29");
+    }
+
+    #[test]
+    fn ages_read_naturally() {
+        assert_eq!(age(100, 130), "just now");
+        assert_eq!(age(0, 3 * 86_400), "3 days ago");
+        assert_eq!(age(0, 3_600), "1 hour ago");
+    }
+}
@@ -0,0 +1,285 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use glob::{glob, MatchOptions, Pattern};
+use serde::Serialize;
+
+use crate::format::plain_body;
+use crate::git;
+use crate::note::{Kind, Note, Parser};
+
+/// Word overlap above which an edited note still counts as "changed"
+/// rather than one removal plus one addition.
+const SIMILARITY: f64 = 0.5;
+
+/// Notes in a directory (paths relative to it) or at a git revision, for
+/// every file the configured glob matches.
+pub fn load_tree(spec: &str, pattern: &str) -> crate::Result<Vec<Note>> {
+    let parser = Parser::new();
+    let mut notes = Vec::new();
+    let dir = Path::new(spec);
+    if dir.is_dir() {
+        let full = dir.join(pattern);
+        for entry in glob(&full.to_string_lossy())? {
+            let path = match entry {
+                Ok(path) if path.is_file() => path,
+                Ok(_) => continue,
+                Err(e) => {
+                    tracing::warn!(error = %e, "glob entry error");
+                    continue;
+                }
+            };
+            let source = fs::read_to_string(&path)?;
+            let relative = path.strip_prefix(dir).unwrap_or(&path);
+            notes.extend(parser.parse_file(relative, &source));
+        }
+    } else {
+        let pattern = Pattern::new(pattern)?;
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::new()
+        };
+        for path in git::tree_files(spec)? {
+            if pattern.matches_path_with(&path, options) {
+                let source = git::show(spec, &path)?;
+                notes.extend(parser.parse_file(&path, &source));
+            }
+        }
+    }
+    Ok(notes)
+}
+
+/// One side of a diff entry, as printed and serialized.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DiffNote {
+    pub path: PathBuf,
+    pub line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub term: Option<String>,
+    pub text: String,
+}
+
+impl From<&Note> for DiffNote {
+    fn from(note: &Note) -> Self {
+        Self {
+            path: note.path.clone(),
+            line: note.line,
+            term: match &note.kind {
+                Kind::Define { term } => Some(term.clone()),
+                _ => None,
+            },
+            text: note.text.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Pair {
+    pub from: DiffNote,
+    pub to: DiffNote,
+}
+
+/// Changes for one kind of note.
+#[derive(Debug, Default, Serialize)]
+pub struct KindDiff {
+    pub added: Vec<DiffNote>,
+    pub removed: Vec<DiffNote>,
+    /// Same text, now in a different file.
+    pub moved: Vec<Pair>,
+    /// Edited text (or a redefined term) in the same file.
+    pub changed: Vec<Pair>,
+}
+
+impl KindDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.moved.is_empty()
+            && self.changed.is_empty()
+    }
+}
+
+/// `notes diff` result, grouped by kind.
+#[derive(Debug, Default, Serialize)]
+pub struct NotesDiff {
+    pub fixme: KindDiff,
+    pub todo: KindDiff,
+    pub define: KindDiff,
+    pub note: KindDiff,
+}
+
+impl NotesDiff {
+    /// Kinds in report order with their headings.
+    pub fn sections(&self) -> [(&'static str, &KindDiff); 4] {
+        [
+            ("FIXME", &self.fixme),
+            ("TODO", &self.todo),
+            ("Definitions", &self.define),
+            ("Notes", &self.note),
+        ]
+    }
+
+    fn kind_mut(&mut self, kind: &Kind) -> &mut KindDiff {
+        match kind {
+            Kind::Fixme => &mut self.fixme,
+            Kind::Todo => &mut self.todo,
+            Kind::Define { .. } => &mut self.define,
+            Kind::Note => &mut self.note,
+        }
+    }
+}
+
+/// Match `old` against `new`: first identical notes (same kind and text,
+/// preferring the same file and nearest line), then edits of a note in the
+/// same file (same term, or similar text). Whatever is left was added or
+/// removed. Notes that only shifted lines within a file are not reported.
+pub fn diff(old: &[Note], new: &[Note]) -> NotesDiff {
+    let mut out = NotesDiff::default();
+    let mut old_left: HashSet<usize> = (0..old.len()).collect();
+    let mut new_left: HashSet<usize> = (0..new.len()).collect();
+
+    for (i, a) in old.iter().enumerate() {
+        let found = best_match(a, new, &new_left, |b| a.kind == b.kind && a.text == b.text);
+        if let Some(j) = found {
+            old_left.remove(&i);
+            new_left.remove(&j);
+            if a.path != new[j].path {
+                out.kind_mut(&a.kind).moved.push(pair(a, &new[j]));
+            }
+        }
+    }
+
+    for (i, a) in old.iter().enumerate() {
+        if !old_left.contains(&i) {
+            continue;
+        }
+        let found = best_match(a, new, &new_left, |b| {
+            a.path == b.path && same_kind(&a.kind, &b.kind) && edited(a, b)
+        });
+        if let Some(j) = found {
+            old_left.remove(&i);
+            new_left.remove(&j);
+            out.kind_mut(&a.kind).changed.push(pair(a, &new[j]));
+        }
+    }
+
+    for (i, a) in old.iter().enumerate() {
+        if old_left.contains(&i) {
+            out.kind_mut(&a.kind).removed.push(a.into());
+        }
+    }
+    for (j, b) in new.iter().enumerate() {
+        if new_left.contains(&j) {
+            out.kind_mut(&b.kind).added.push(b.into());
+        }
+    }
+    out
+}
+
+/// Unclaimed note in `candidates` passing `pred`, same file first, then
+/// nearest line, then earliest.
+fn best_match(
+    note: &Note,
+    candidates: &[Note],
+    left: &HashSet<usize>,
+    pred: impl Fn(&Note) -> bool,
+) -> Option<usize> {
+    candidates
+        .iter()
+        .enumerate()
+        .filter(|(j, b)| left.contains(j) && pred(b))
+        .min_by_key(|(j, b)| (b.path != note.path, b.line.abs_diff(note.line), *j))
+        .map(|(j, _)| j)
+}
+
+fn same_kind(a: &Kind, b: &Kind) -> bool {
+    std::mem::discriminant(a) == std::mem::discriminant(b)
+}
+
+fn edited(a: &Note, b: &Note) -> bool {
+    match (&a.kind, &b.kind) {
+        (Kind::Define { term: ta }, Kind::Define { term: tb }) if ta.eq_ignore_ascii_case(tb) => {
+            true
+        }
+        _ => similarity(&a.text, &b.text) >= SIMILARITY,
+    }
+}
+
+/// Jaccard overlap of the lowercased word sets.
+fn similarity(a: &str, b: &str) -> f64 {
+    let words = |s: &str| -> HashSet<String> {
+        plain_body(s)
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect()
+    };
+    let (a, b) = (words(a), words(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+fn pair(a: &Note, b: &Note) -> Pair {
+    Pair {
+        from: a.into(),
+        to: b.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(path: &str, source: &str) -> Vec<Note> {
+        Parser::new().parse_file(Path::new(path), source)
+    }
+
+    #[test]
+    fn reports_added_removed_moved_and_changed() {
+        let mut old = parse(
+            "ch01.md",
+            "<!-- FIXME timeline broken in chapter four -->\n\
+             <!-- TODO rewrite the prologue -->\n\
+             <!-- NOTE def tithe a tenth of the crop. -->\n\
+             <!-- NOTE the knives march at dawn -->\n",
+        );
+        old.extend(parse("ch02.md", "<!-- FIXME spelling of Athrune -->\n"));
+
+        let mut new = parse(
+            "ch01.md",
+            "\n\n<!-- TODO rewrite the prologue and the epilogue -->\n\
+             <!-- NOTE def tithe a tenth of every harvest. -->\n\
+             <!-- TODO add a map -->\n",
+        );
+        new.extend(parse(
+            "ch03.md",
+            "<!-- NOTE the knives march at dawn -->\n<!-- FIXME spelling of Athrune -->\n",
+        ));
+
+        let d = diff(&old, &new);
+        assert_eq!(d.fixme.removed.len(), 1);
+        assert_eq!(d.fixme.removed[0].text, "timeline broken in chapter four");
+        assert!(d.fixme.added.is_empty());
+        assert_eq!(d.fixme.moved.len(), 1);
+        assert_eq!(d.fixme.moved[0].to.path, Path::new("ch03.md"));
+        assert_eq!(d.todo.changed.len(), 1);
+        assert_eq!(d.todo.added[0].text, "add a map");
+        assert_eq!(d.define.changed.len(), 1);
+        assert_eq!(d.note.moved.len(), 1);
+    }
+
+    #[test]
+    fn line_shifts_alone_are_not_changes() {
+        let old = parse("ch01.md", "<!-- TODO a -->\n");
+        let new = parse("ch01.md", "\n\n\n<!-- TODO a -->\n");
+        assert!(diff(&old, &new)
+            .sections()
+            .iter()
+            .all(|(_, k)| k.is_empty()));
+    }
+}
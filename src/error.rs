@@ -7,6 +7,7 @@ pub enum Error {
     Abseil(abseil::Error),
     Config(String),
     Glob(glob::PatternError),
+    Query(String),
     Message(String),
 }
 
@@ -42,6 +43,7 @@ impl fmt::Display for Error {
             Error::Abseil(e) => e.fmt(f),
             Error::Config(e) => write!(f, "{e}"),
             Error::Glob(e) => e.fmt(f),
+            Error::Query(e) => write!(f, "query: {e}"),
             Error::Message(e) => write!(f, "{e}"),
         }
     }
@@ -0,0 +1,165 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::format::plain_body;
+use crate::note::{Kind, Note, Status};
+
+/// How marked TUI notes are yanked or exported.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    #[default]
+    Text,
+    Markdown,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Markdown => "markdown",
+            Self::Json => "json",
+        }
+    }
+
+    /// The next format, for cycling with tab.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Text => Self::Markdown,
+            Self::Markdown => Self::Json,
+            Self::Json => Self::Text,
+        }
+    }
+}
+
+/// A note as exported to JSON.
+#[derive(Serialize)]
+struct ExportNote<'a> {
+    path: &'a PathBuf,
+    line: usize,
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    term: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<&'static str>,
+    tags: &'a [String],
+    text: &'a str,
+}
+
+impl<'a> From<&'a Note> for ExportNote<'a> {
+    fn from(note: &'a Note) -> Self {
+        Self {
+            path: &note.path,
+            line: note.line,
+            kind: kind_label(note),
+            term: match &note.kind {
+                Kind::Define { term } => Some(term),
+                _ => None,
+            },
+            status: note.status.label(),
+            tags: &note.tags,
+            text: &note.text,
+        }
+    }
+}
+
+fn kind_label(note: &Note) -> &'static str {
+    match note.kind {
+        Kind::Note => "NOTE",
+        Kind::Fixme => "FIXME",
+        Kind::Todo => "TODO",
+        Kind::Define { .. } => "DEFINE",
+    }
+}
+
+fn location(note: &Note) -> String {
+    format!("{}:{}", note.path.display(), note.line)
+}
+
+/// `notes` as one document in `format`.
+pub fn render(notes: &[&Note], format: ExportFormat) -> crate::Result<String> {
+    Ok(match format {
+        ExportFormat::Text => text(notes),
+        ExportFormat::Markdown => markdown(notes),
+        ExportFormat::Json => {
+            let notes: Vec<ExportNote> = notes.iter().map(|n| ExportNote::from(*n)).collect();
+            serde_json::to_string_pretty(&notes)? + "\n"
+        }
+    })
+}
+
+/// A `KIND  path:line` header over each body, blank lines between notes.
+fn text(notes: &[&Note]) -> String {
+    let mut out = String::new();
+    for (i, note) in notes.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let header = match (&note.kind, note.status.label()) {
+            (Kind::Define { term }, _) => format!("{term}  {}", location(note)),
+            (_, Some(status)) => format!("{} ({status})  {}", kind_label(note), location(note)),
+            (_, None) => format!("{}  {}", kind_label(note), location(note)),
+        };
+        out.push_str(&format!("{header}\n{}\n", plain_body(&note.text)));
+    }
+    out
+}
+
+/// One list item per note; FIXMEs and TODOs as task items.
+fn markdown(notes: &[&Note]) -> String {
+    let mut out = String::new();
+    for note in notes {
+        let task = match (&note.kind, note.status) {
+            (Kind::Fixme | Kind::Todo, Status::Open) => "[ ] ",
+            (Kind::Fixme | Kind::Todo, _) => "[x] ",
+            _ => "",
+        };
+        let lead = match &note.kind {
+            Kind::Define { term } => format!("**{term}**: "),
+            Kind::Note => String::new(),
+            _ => format!("**{}** ", kind_label(note)),
+        };
+        // The source text, tags and all, on one line.
+        let body = note.text.split_whitespace().collect::<Vec<_>>().join(" ");
+        out.push_str(&format!("- {task}{lead}{body} — `{}`\n", location(note)));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::Parser;
+    use std::path::Path;
+
+    fn notes() -> Vec<Note> {
+        Parser::new().parse_file(
+            Path::new("ch01.md"),
+            "<!-- FIXME the bridge #army -->\n<!-- TODO [x] ride -->\n\
+             <!-- NOTE def Knife a blade -->\n",
+        )
+    }
+
+    #[test]
+    fn renders_each_format() {
+        let notes = notes();
+        let notes: Vec<&Note> = notes.iter().collect();
+        assert_eq!(
+            render(&notes[..2], ExportFormat::Text).unwrap(),
+            "FIXME  ch01.md:1\nthe bridge army\n\nTODO (done)  ch01.md:2\nride\n"
+        );
+        assert_eq!(
+            render(&notes, ExportFormat::Markdown).unwrap(),
+            "- [ ] **FIXME** the bridge #army — `ch01.md:1`\n\
+             - [x] **TODO** ride — `ch01.md:2`\n\
+             - **Knife**: a blade — `ch01.md:3`\n"
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&render(&notes[1..], ExportFormat::Json).unwrap()).unwrap();
+        assert_eq!(json[0]["status"], "done");
+        assert_eq!(json[1]["kind"], "DEFINE");
+        assert_eq!(json[1]["term"], "Knife");
+        assert!(json[0].get("term").is_none());
+    }
+}
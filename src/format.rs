@@ -1,34 +1,111 @@
 use std::io;
 
-use hyphenation::{Hyphenator, Language, Load, Standard};use owo_colors::OwoColorize;
+use hyphenation::{Hyphenator, Load, Standard};
+use owo_colors::OwoColorize;
 use textwrap::termwidth;
 
+use crate::diff::{DiffNote, NotesDiff};
+use crate::language::{Language, LanguageMap};
+use crate::listing::{Group, GroupBy};
 use crate::note::{Kind, Note};
+use crate::stats::Stats;
+use crate::theme::{StyleSpec, Theme};
 
 pub struct Formatter {
-    width: usize,
-    dictionary: Standard,
+    /// Body wrap column; `None` prints each body on one line.
+    width: Option<usize>,
+    languages: LanguageMap,
+    /// Loaded per configured language; empty when hyphenation is off.
+    dictionaries: Vec<(Language, Standard)>,
+    theme: Theme,
+    /// When false, output carries no escape codes at all.
+    color: bool,
 }
 
 impl Formatter {
-    pub fn new() -> Self {
-        let dictionary = Standard::from_embedded(Language::EnglishUS).unwrap();
+    pub fn new(languages: LanguageMap, hyphenate: bool) -> Self {
+        let dictionaries = if hyphenate {
+            languages
+                .languages()
+                .into_iter()
+                .filter_map(|lang| load_dictionary(lang).map(|dict| (lang, dict)))
+                .collect()
+        } else {
+            Vec::new()
+        };
         Self {
-            width: termwidth().min(80),
-            dictionary,
+            width: Some(termwidth().min(80)),
+            languages,
+            dictionaries,
+            theme: Theme::default(),
+            color: true,
         }
     }
 
-    pub fn fmt_note(&self, mut w: impl io::Write, note: &Note) -> io::Result<()> {
-        let loc = format!("{}:{}", note.path.display(), note.line);
+    pub fn with_theme(self, theme: Theme) -> Self {
+        Self { theme, ..self }
+    }
+
+    pub fn without_color(self) -> Self {
+        Self {
+            color: false,
+            ..self
+        }
+    }
+
+    pub fn with_width(self, width: usize) -> Self {
+        Self {
+            width: Some(width),
+            ..self
+        }
+    }
+
+    pub fn without_wrap(self) -> Self {
+        Self {
+            width: None,
+            ..self
+        }
+    }
+
+    /// Wrap a note body to `width` columns with the note's hyphenation rules.
+    pub fn wrap_body(&self, note: &Note, width: usize) -> Vec<Vec<BodyWord>> {
+        let words = styled_words(&note.text);
+        wrap_words(&words, width.max(1), self.dictionary_for(note))
+    }
+
+    fn dictionary_for(&self, note: &Note) -> Option<&Standard> {
+        let lang = self.languages.language_for(&note.path);
+        self.dictionaries
+            .iter()
+            .find(|(l, _)| *l == lang)
+            .map(|(_, dict)| dict)
+    }
+
+    pub fn fmt_note(&self, w: impl io::Write, note: &Note) -> io::Result<()> {
+        self.fmt_note_at(w, note, true)
+    }
+
+    /// Without `show_path`, the header carries only the line number (the
+    /// path is already in a section header).
+    fn fmt_note_at(&self, mut w: impl io::Write, note: &Note, show_path: bool) -> io::Result<()> {
+        let loc = if show_path {
+            format!("{}:{}", note.path.display(), note.line)
+        } else {
+            format!("line {}", note.line)
+        };
+        let loc = self.paint(&loc, &self.theme.location);
+        let status = match note.status.label() {
+            Some(label) => format!(" ({label})"),
+            None => String::new(),
+        };
         let header = match &note.kind {
-            Kind::Define { term } => format!("{}  {}", term.bold(), loc.dimmed()),
-            Kind::Fixme => format!("{}  {}", "FIXME".red().bold(), loc.dimmed()),
-            Kind::Todo => format!("{}  {}", "TODO".yellow().bold(), loc.dimmed()),
-            Kind::Note => loc.dimmed().to_string(),
+            Kind::Define { term } => format!("{}  {loc}", self.paint(term, &self.theme.term)),
+            Kind::Fixme => format!("{}{status}  {loc}", self.paint("FIXME", &self.theme.fixme)),
+            Kind::Todo => format!("{}{status}  {loc}", self.paint("TODO", &self.theme.todo)),
+            Kind::Note => loc,
         };
         writeln!(w, "{header}")?;
-        self.write_body(&mut w, &note.text)?;
+        self.write_body(&mut w, note)?;
         Ok(())
     }
 
@@ -44,6 +121,85 @@ impl Formatter {
         Ok(())
     }
 
+    /// Sections from [`crate::listing::group`], each under a heading. File
+    /// sections print the path once instead of on every note.
+    pub fn fmt_groups(
+        &self,
+        mut w: impl io::Write,
+        groups: &[Group],
+        by: GroupBy,
+    ) -> io::Result<()> {
+        for (i, group) in groups.iter().enumerate() {
+            if i > 0 {
+                writeln!(w)?;
+            }
+            writeln!(w, "{}", self.paint(&group.title, &self.theme.heading))?;
+            for note in &group.notes {
+                writeln!(w)?;
+                self.fmt_note_at(&mut w, note, by != GroupBy::File)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn fmt_stats(&self, mut w: impl io::Write, stats: &Stats) -> io::Result<()> {
+        for (i, section) in stats.sections().iter().enumerate() {
+            if i > 0 {
+                writeln!(w)?;
+            }
+            writeln!(w, "{}", self.paint(section.title, &self.theme.heading))?;
+            let width = section.rows.iter().map(|(l, _)| l.chars().count()).max();
+            for (label, value) in &section.rows {
+                writeln!(w, "  {label:<width$}  {value}", width = width.unwrap_or(0))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// `notes diff` report: one section per kind with changes, then
+    /// `-` removed, `+` added, `>` moved, and `~` changed notes.
+    pub fn fmt_diff(&self, mut w: impl io::Write, diff: &NotesDiff) -> io::Result<()> {
+        let mut first = true;
+        for (title, kind) in diff.sections() {
+            if kind.is_empty() {
+                continue;
+            }
+            if !first {
+                writeln!(w)?;
+            }
+            first = false;
+            writeln!(w, "{}", self.paint(title, &self.theme.heading))?;
+            for note in &kind.removed {
+                let mark = self.paint("-", &self.theme.removed);
+                writeln!(w, "{mark} {}  {}", self.diff_loc(note), diff_text(note))?;
+            }
+            for note in &kind.added {
+                let mark = self.paint("+", &self.theme.added);
+                writeln!(w, "{mark} {}  {}", self.diff_loc(note), diff_text(note))?;
+            }
+            for pair in &kind.moved {
+                let (from, to) = (self.diff_loc(&pair.from), self.diff_loc(&pair.to));
+                writeln!(w, "> {from} → {to}  {}", diff_text(&pair.to))?;
+            }
+            for pair in &kind.changed {
+                let (from, to) = (self.diff_loc(&pair.from), self.diff_loc(&pair.to));
+                writeln!(w, "~ {from} → {to}")?;
+                let old = self.paint(&diff_text(&pair.from), &self.theme.removed);
+                let new = self.paint(&diff_text(&pair.to), &self.theme.added);
+                writeln!(w, "    {old}\n    {new}")?;
+            }
+        }
+        if first {
+            writeln!(w, "no note changes")?;
+        }
+        Ok(())
+    }
+
+    fn diff_loc(&self, note: &DiffNote) -> String {
+        let loc = format!("{}:{}", note.path.display(), note.line);
+        self.paint(&loc, &self.theme.location)
+    }
+
     pub fn fmt_glossary(&self, mut w: impl io::Write, notes: &[&Note]) -> io::Result<()> {
         let mut first = true;
         for note in notes {
@@ -55,27 +211,90 @@ impl Formatter {
             }
             first = false;
 
-            writeln!(w, "{}", term.bold())?;
-            self.write_body(&mut w, &note.text)?;
+            writeln!(w, "{}", self.paint(term, &self.theme.term))?;
+            self.write_body(&mut w, note)?;
         }
         Ok(())
     }
 
-    fn write_body(&self, w: &mut impl io::Write, text: &str) -> io::Result<()> {
-        let words = styled_words(text);
+    fn write_body(&self, w: &mut impl io::Write, note: &Note) -> io::Result<()> {
         let indent = "  ";
-        let max = self.width.saturating_sub(indent.len()).max(1);
+        let Some(width) = self.width else {
+            return self.write_styled_line(w, indent, &styled_words(&note.text));
+        };
 
-        for line in wrap_words(&words, max, &self.dictionary) {
-            write_styled_line(w, indent, &line)?;
+        for line in self.wrap_body(note, width.saturating_sub(indent.len())) {
+            self.write_styled_line(w, indent, &line)?;
         }
         Ok(())
     }
+
+    fn write_styled_line(
+        &self,
+        w: &mut impl io::Write,
+        indent: &str,
+        words: &[BodyWord],
+    ) -> io::Result<()> {
+        write!(w, "{indent}")?;
+        for (i, word) in words.iter().enumerate() {
+            if i > 0 {
+                write!(w, " ")?;
+            }
+            for (text, style) in &word.segments {
+                write!(w, "{}", self.paint_word(text, *style))?;
+            }
+        }
+        writeln!(w)
+    }
+
+    fn paint(&self, text: &str, spec: &StyleSpec) -> String {
+        if self.color {
+            text.style(spec.cli()).to_string()
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn paint_word(&self, w: &str, style: BodyStyle) -> String {
+        if !self.color || style == BodyStyle::default() {
+            return w.to_string();
+        }
+        let mut spec = if style.tag {
+            self.theme.tag.clone()
+        } else {
+            StyleSpec::default()
+        };
+        spec.bold |= style.bold;
+        spec.italic |= style.italic;
+        w.style(spec.cli()).to_string()
+    }
 }
 
 impl Default for Formatter {
     fn default() -> Self {
-        Self::new()
+        Self::new(LanguageMap::default(), true)
+    }
+}
+
+/// One-line body for diff rows; definitions lead with their term.
+fn diff_text(note: &DiffNote) -> String {
+    match &note.term {
+        Some(term) => format!("{term}: {}", plain_body(&note.text)),
+        None => plain_body(&note.text),
+    }
+}
+
+fn load_dictionary(lang: Language) -> Option<Standard> {
+    let Some(embedded) = lang.hyphenation() else {
+        tracing::warn!(%lang, "no hyphenation dictionary built in; wrapping without hyphens");
+        return None;
+    };
+    match Standard::from_embedded(embedded) {
+        Ok(dict) => Some(dict),
+        Err(e) => {
+            tracing::warn!(%lang, error = %e, "failed to load hyphenation dictionary");
+            None
+        }
     }
 }
 
@@ -174,22 +393,10 @@ pub fn plain_body(text: &str) -> String {
         .join(" ")
 }
 
-fn write_styled_line(w: &mut impl io::Write, indent: &str, words: &[BodyWord]) -> io::Result<()> {
-    write!(w, "{indent}")?;
-    for (i, word) in words.iter().enumerate() {
-        if i > 0 {
-            write!(w, " ")?;
-        }
-        for (text, style) in &word.segments {
-            write!(w, "{}", paint_word(text, *style))?;
-        }
-    }
-    writeln!(w)
-}
-
-/// Greedy word wrap with end-of-line hyphenation. Returns words per line; each
-/// word may carry multiple styled segments (e.g. tag name + plain punctuation).
-fn wrap_words(words: &[BodyWord], max: usize, dict: &Standard) -> Vec<Vec<BodyWord>> {
+/// Greedy word wrap with end-of-line hyphenation (plain wrapping without a
+/// dictionary). Returns words per line; each word may carry multiple styled
+/// segments (e.g. tag name + plain punctuation).
+fn wrap_words(words: &[BodyWord], max: usize, dict: Option<&Standard>) -> Vec<Vec<BodyWord>> {
     use std::collections::VecDeque;
 
     let mut queue: VecDeque<BodyWord> = words.iter().cloned().collect();
@@ -216,11 +423,9 @@ fn wrap_words(words: &[BodyWord], max: usize, dict: &Standard) -> Vec<Vec<BodyWo
 
         // Hyphenate only single-segment words; multi-segment (e.g. tag + punct) are
         // short and left intact.
-        let split = if word.segments.len() == 1 {
-            let (t, s) = &word.segments[0];
-            split_word(t, dict, max).map(|(head, tail)| (head, tail, *s))
-        } else {
-            None
+        let split = match (dict, word.segments.as_slice()) {
+            (Some(dict), [(t, s)]) => split_word(t, dict, max).map(|(head, tail)| (head, tail, *s)),
+            _ => None,
         };
 
         match split {
@@ -404,19 +609,6 @@ fn push_span(out: &mut Vec<BodySpan>, text: String, style: BodyStyle) {
     out.push(BodySpan { text, style });
 }
 
-fn paint_word(w: &str, style: BodyStyle) -> String {
-    match (style.bold, style.italic, style.tag) {
-        (true, true, true) => w.bold().italic().underline().to_string(),
-        (true, true, false) => w.bold().italic().to_string(),
-        (true, false, true) => w.bold().underline().to_string(),
-        (true, false, false) => w.bold().to_string(),
-        (false, true, true) => w.italic().underline().to_string(),
-        (false, true, false) => w.italic().to_string(),
-        (false, false, true) => w.underline().to_string(),
-        (false, false, false) => w.to_string(),
-    }
-}
-
 fn tag_parts(rest: &str) -> (&str, &str) {
     let end = rest
         .char_indices()
@@ -434,6 +626,8 @@ fn tag_parts(rest: &str) -> (&str, &str) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::listing;
+    use crate::note::Status;
 
     #[test]
     fn trailing_tags_are_kept_as_tags() {
@@ -531,9 +725,13 @@ mod tests {
         assert!(words[2].style_flags().italic);
     }
 
+    fn en_us() -> Standard {
+        Standard::from_embedded(hyphenation::Language::EnglishUS).unwrap()
+    }
+
     #[test]
     fn split_word_fits_largest_prefix() {
-        let dict = Standard::from_embedded(Language::EnglishUS).unwrap();
+        let dict = en_us();
         // "hyphenation" breaks at several points; with avail 6 we want the
         // largest head (5 chars + hyphen) that the dictionary permits.
         let (head, tail) = split_word("hyphenation", &dict, 6).expect("should split");
@@ -544,15 +742,15 @@ mod tests {
 
     #[test]
     fn split_word_skips_short_words() {
-        let dict = Standard::from_embedded(Language::EnglishUS).unwrap();
+        let dict = en_us();
         assert!(split_word("cat", &dict, 5).is_none());
     }
 
     #[test]
     fn wrap_hyphenates_overflowing_word() {
-        let dict = Standard::from_embedded(Language::EnglishUS).unwrap();
+        let dict = en_us();
         let words = styled_words("a wordy antidisestablishmentarianism ends here");
-        let lines = wrap_words(&words, 14, &dict);
+        let lines = wrap_words(&words, 14, Some(&dict));
 
         // Each rendered line fits the width.
         for line in &lines {
@@ -591,4 +789,93 @@ mod tests {
         }
         assert_eq!(rejoined, "a wordy antidisestablishmentarianism ends here");
     }
+
+    #[test]
+    fn wrap_without_dictionary_does_not_hyphenate() {
+        let words = styled_words("a wordy antidisestablishmentarianism ends here");
+        let lines = wrap_words(&words, 14, None);
+        assert!(lines.iter().flatten().all(|w| !w.text().ends_with('-')));
+        let texts: Vec<String> = lines
+            .iter()
+            .map(|l| l.iter().map(|w| w.text()).collect::<Vec<_>>().join(" "))
+            .collect();
+        assert_eq!(
+            texts,
+            vec!["a wordy", "antidisestablishmentarianism", "ends here"]
+        );
+    }
+
+    #[test]
+    fn dictionary_follows_file_language() {
+        let mut config = crate::configuration::DirConfig::new("**/*.md");
+        config.languages.insert("de/*".into(), Language::German);
+        let formatter = Formatter::new(LanguageMap::new(&config).unwrap(), true);
+        let note = |path: &str| Note {
+            path: path.into(),
+            line: 1,
+            kind: Kind::Note,
+            status: Status::Open,
+            tags: vec![],
+            text: String::new(),
+        };
+
+        assert!(formatter.dictionary_for(&note("ch01.md")).is_some());
+        assert_eq!(
+            formatter.dictionary_for(&note("de/ch01.md")).is_some(),
+            cfg!(feature = "hyphenate-all")
+        );
+
+        let plain = Formatter::new(LanguageMap::new(&config).unwrap(), false);
+        assert!(plain.dictionary_for(&note("ch01.md")).is_none());
+    }
+
+    #[test]
+    fn width_and_no_wrap() {
+        let note = Note {
+            path: "t.md".into(),
+            line: 1,
+            kind: Kind::Note,
+            status: Status::Open,
+            tags: vec![],
+            text: "the quick brown fox jumps over the lazy dog again and again".into(),
+        };
+        let render = |f: Formatter| {
+            let mut out = Vec::new();
+            f.fmt_note(&mut out, &note).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let narrow = render(Formatter::default().with_width(22));
+        let body: Vec<&str> = narrow.lines().skip(1).collect();
+        assert!(body.len() > 2);
+        assert!(body.iter().all(|l| l.chars().count() <= 22), "{body:?}");
+
+        let flat = render(Formatter::default().without_wrap());
+        let body: Vec<&str> = flat.lines().skip(1).collect();
+        assert_eq!(body, vec![format!("  {}", note.text)]);
+    }
+
+    #[test]
+    fn group_by_file_prints_each_path_once() {
+        let note = |path: &str, line| Note {
+            path: path.into(),
+            line,
+            kind: Kind::Fixme,
+            status: Status::Open,
+            tags: vec![],
+            text: "check this".into(),
+        };
+        let notes = [note("ch01.md", 3), note("ch01.md", 9), note("ch02.md", 1)];
+        let refs: Vec<&Note> = notes.iter().collect();
+        let mut out = Vec::new();
+        let groups = listing::group(&refs, GroupBy::File);
+        Formatter::default()
+            .without_color()
+            .fmt_groups(&mut out, &groups, GroupBy::File)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.matches("ch01.md").count(), 1);
+        assert!(out.contains("FIXME  line 9"), "{out}");
+        assert!(out.starts_with("ch01.md\n\nFIXME  line 3\n"), "{out}");
+    }
 }
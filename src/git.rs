@@ -0,0 +1,325 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use crate::error::Error;
+use crate::note::Note;
+
+/// The commit that last touched a note's line, from `git blame`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Blame {
+    pub commit: String,
+    pub author: String,
+    pub author_mail: String,
+    /// Author time, seconds since the Unix epoch.
+    pub time: i64,
+}
+
+impl Blame {
+    /// Lines not committed yet blame to the all-zero commit.
+    pub fn is_committed(&self) -> bool {
+        self.commit.bytes().any(|b| b != b'0')
+    }
+
+    /// What `git blame` reports for a line not committed yet.
+    fn uncommitted() -> Self {
+        Self {
+            commit: "0".repeat(40),
+            author: "Not Committed Yet".into(),
+            author_mail: "not.committed.yet".into(),
+            time: 0,
+        }
+    }
+
+    pub fn short_commit(&self) -> &str {
+        &self.commit[..self.commit.len().min(8)]
+    }
+}
+
+/// Lazy `git blame` per file, using the local `git` binary only.
+#[derive(Default)]
+pub struct History {
+    /// Blame per line (index = line - 1), one `git blame` run per file.
+    files: HashMap<PathBuf, Vec<Blame>>,
+    /// `(commit, rev)` → commit is an ancestor of rev.
+    ancestors: HashMap<(String, String), bool>,
+}
+
+impl History {
+    pub fn blame(&mut self, note: &Note) -> crate::Result<Option<&Blame>> {
+        if !self.files.contains_key(&note.path) {
+            let lines = blame_file(&note.path)?;
+            self.files.insert(note.path.clone(), lines);
+        }
+        Ok(self.files[&note.path].get(note.line.wrapping_sub(1)))
+    }
+
+    /// True when `blame`'s commit is already in `rev` (so the note predates it).
+    fn in_rev(&mut self, path: &Path, commit: &str, rev: &str) -> crate::Result<bool> {
+        let key = (commit.to_string(), rev.to_string());
+        if let Some(&known) = self.ancestors.get(&key) {
+            return Ok(known);
+        }
+        let status = git(path)
+            .args(["merge-base", "--is-ancestor", commit, rev])
+            .stderr(Stdio::null())
+            .status()?;
+        let known = match status.code() {
+            Some(0) => true,
+            Some(1) => false,
+            _ => return Err(Error::Message(format!("git: unknown revision {rev:?}"))),
+        };
+        self.ancestors.insert(key, known);
+        Ok(known)
+    }
+}
+
+/// `--since`, `--author`, and `--new-since` filters for listings.
+#[derive(Debug, Default)]
+pub struct HistoryFilter {
+    pub since: Option<i64>,
+    pub author: Option<String>,
+    pub new_since: Option<String>,
+}
+
+impl HistoryFilter {
+    pub fn is_empty(&self) -> bool {
+        self.since.is_none() && self.author.is_none() && self.new_since.is_none()
+    }
+
+    pub fn matches(&self, history: &mut History, note: &Note) -> crate::Result<bool> {
+        let Some(blame) = history.blame(note)?.cloned() else {
+            return Ok(false);
+        };
+        if let Some(since) = self.since {
+            if blame.is_committed() && blame.time < since {
+                return Ok(false);
+            }
+        }
+        if let Some(author) = &self.author {
+            let author = author.to_lowercase();
+            if !blame.author.to_lowercase().contains(&author)
+                && !blame.author_mail.to_lowercase().contains(&author)
+            {
+                return Ok(false);
+            }
+        }
+        if let Some(rev) = &self.new_since {
+            if blame.is_committed() && history.in_rev(&note.path, &blame.commit, rev)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// `git` run from the directory holding `path`, so files in another
+/// repository still resolve.
+fn git(path: &Path) -> Command {
+    let mut command = Command::new("git");
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        command.arg("-C").arg(dir);
+    }
+    command
+}
+
+/// Blame for each line of `path`. A file git does not track yet (a new
+/// chapter, say) is all uncommitted; any other failure, such as no
+/// repository or no `git`, is an error.
+fn blame_file(path: &Path) -> crate::Result<Vec<Blame>> {
+    let name = path.file_name().unwrap_or(path.as_os_str());
+    match run(git(path)
+        .args(["blame", "--line-porcelain", "--"])
+        .arg(name))
+    {
+        Ok(out) => Ok(parse_line_porcelain(&out)),
+        Err(e) if is_untracked(path)? => {
+            tracing::debug!(path = %path.display(), error = %e, "untracked, so uncommitted");
+            let lines = fs::read_to_string(path)?.lines().count();
+            Ok(vec![Blame::uncommitted(); lines])
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// True when `path` sits in a repository that does not track it.
+fn is_untracked(path: &Path) -> crate::Result<bool> {
+    let name = path.file_name().unwrap_or(path.as_os_str());
+    let output = git(path)
+        .args(["ls-files", "--error-unmatch", "--"])
+        .arg(name)
+        .output()
+        .map_err(|e| Error::Message(format!("git: {e}")))?;
+    match output.status.code() {
+        Some(0) => Ok(false),
+        Some(1) => Ok(true),
+        _ => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Err(Error::Message(format!("git: {}", stderr.trim())))
+        }
+    }
+}
+
+/// Fail unless `rev` names a commit, so a typo in `--new-since` is reported
+/// even when no note needs the ancestry check.
+pub fn verify_rev(rev: &str) -> crate::Result<()> {
+    let spec = format!("{rev}^{{commit}}");
+    run(Command::new("git").args(["rev-parse", "--verify", "--quiet", &spec]))
+        .map(drop)
+        .map_err(|_| Error::Message(format!("git: unknown revision {rev:?}")))
+}
+
+/// Files at `rev` under the working directory, relative to it.
+pub fn tree_files(rev: &str) -> crate::Result<Vec<PathBuf>> {
+    let out = run(Command::new("git").args(["ls-tree", "-r", "-z", "--name-only", rev]))?;
+    Ok(out
+        .split('\0')
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Contents of `path` (relative to the working directory) at `rev`.
+pub fn show(rev: &str, path: &Path) -> crate::Result<String> {
+    let spec = format!("{rev}:./{}", path.display());
+    run(Command::new("git").args(["show", &spec]))
+}
+
+fn run(command: &mut Command) -> crate::Result<String> {
+    let output = command
+        .output()
+        .map_err(|e| Error::Message(format!("git: {e}")))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::Message(format!("git: {}", stderr.trim())));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parse `git blame --line-porcelain`: a `<sha> <orig> <final> [n]` header,
+/// `key value` lines, then the line itself prefixed with a tab.
+fn parse_line_porcelain(out: &str) -> Vec<Blame> {
+    let mut lines = Vec::new();
+    let mut current: Option<Blame> = None;
+    for line in out.lines() {
+        if line.starts_with('\t') {
+            lines.extend(current.take());
+            continue;
+        }
+        match &mut current {
+            None => {
+                let commit = line.split(' ').next().unwrap_or_default();
+                current = Some(Blame {
+                    commit: commit.to_string(),
+                    author: String::new(),
+                    author_mail: String::new(),
+                    time: 0,
+                });
+            }
+            Some(blame) => {
+                if let Some(author) = line.strip_prefix("author ") {
+                    blame.author = author.to_string();
+                } else if let Some(mail) = line.strip_prefix("author-mail ") {
+                    blame.author_mail = mail.trim_matches(['<', '>']).to_string();
+                } else if let Some(time) = line.strip_prefix("author-time ") {
+                    blame.time = time.parse().unwrap_or(0);
+                }
+            }
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PORCELAIN: &str = "\
+4f32940aa4f32940aa4f32940aa4f32940aa4f32 1 1 2
+author Ada Lovelace
+author-mail <ada@example.com>
+author-time 1760000000
+author-tz +0000
+summary Add notes
+filename ch01.md
+\t<!-- FIXME one -->
+0000000000000000000000000000000000000000 2 2
+author Not Committed Yet
+author-mail <not.committed.yet>
+author-time 1770000000
+filename ch01.md
+\t<!-- TODO two -->
+";
+
+    #[test]
+    fn parses_line_porcelain() {
+        let blame = parse_line_porcelain(PORCELAIN);
+        assert_eq!(blame.len(), 2);
+        assert_eq!(blame[0].author, "Ada Lovelace");
+        assert_eq!(blame[0].author_mail, "ada@example.com");
+        assert_eq!(blame[0].time, 1_760_000_000);
+        assert_eq!(blame[0].short_commit(), "4f32940a");
+        assert!(blame[0].is_committed());
+        assert!(!blame[1].is_committed());
+    }
+
+    #[test]
+    fn untracked_files_count_as_uncommitted() {
+        let dir = std::env::temp_dir().join(format!("notes-git-{}", std::process::id()));
+        let outside = dir.join("outside");
+        let repo = dir.join("repo");
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::create_dir_all(&repo).unwrap();
+        let git_in = |args: &[&str]| run(Command::new("git").arg("-C").arg(&repo).args(args));
+        git_in(&["init", "-q"]).unwrap();
+        git_in(&[
+            "-c",
+            "user.name=Ada",
+            "-c",
+            "user.email=ada@example.com",
+            "-c",
+            "commit.gpgsign=false",
+            "commit",
+            "-q",
+            "--allow-empty",
+            "-m",
+            "init",
+        ])
+        .unwrap();
+        let source = "<!-- TODO new chapter -->\n";
+        let parser = crate::note::Parser::new();
+        let path = repo.join("ch09.md");
+        std::fs::write(&path, source).unwrap();
+        let note = parser.parse_file(&path, source).remove(0);
+        let stray_path = outside.join("ch10.md");
+        std::fs::write(&stray_path, source).unwrap();
+        let stray = parser.parse_file(&stray_path, source).remove(0);
+
+        let filter = HistoryFilter {
+            since: Some(i64::MAX),
+            new_since: Some("HEAD".into()),
+            ..HistoryFilter::default()
+        };
+        let by_ada = HistoryFilter {
+            author: Some("ada".into()),
+            ..HistoryFilter::default()
+        };
+        let mut history = History::default();
+        let matched = filter.matches(&mut history, &note);
+        let by_ada_matched = by_ada.matches(&mut history, &note);
+        let outside_repo = filter.matches(&mut History::default(), &stray);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(matched.unwrap());
+        assert!(!by_ada_matched.unwrap());
+        assert!(outside_repo.is_err());
+    }
+
+    #[test]
+    fn unknown_revisions_are_rejected() {
+        assert!(verify_rev("HEAD").is_ok());
+        assert!(verify_rev("no-such-revision").is_err());
+    }
+}
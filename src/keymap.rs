@@ -0,0 +1,574 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    path::PathBuf,
+    str::FromStr,
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+
+use crate::configuration;
+use crate::error::Error;
+
+const FILENAME: &str = "keymap.json";
+
+/// Something a TUI key does. Browse mode uses all of them; the overlays
+/// reuse `down` / `up`, `back`, and the key that opened them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Down,
+    Up,
+    Top,
+    Bottom,
+    PageDown,
+    PageUp,
+    HalfDown,
+    HalfUp,
+    Focus,
+    Open,
+    Back,
+    Narrow,
+    Widen,
+    Source,
+    SourceDown,
+    SourceUp,
+    Glossary,
+    Files,
+    Compare,
+    Filter,
+    Find,
+    FindNext,
+    FindPrev,
+    ToggleTag,
+    ExcludeTag,
+    TagMode,
+    TagSort,
+    Views,
+    SaveView,
+    DeleteView,
+    Errata,
+    Pending,
+    Fts,
+    Stats,
+    Progress,
+    Group,
+    Yank,
+    Mark,
+    MarkAll,
+    Export,
+    ToggleStatus,
+    AddTag,
+    RemoveTag,
+    EditText,
+    Undo,
+    Palette,
+    Help,
+    Quit,
+}
+
+impl Action {
+    pub const ALL: [Action; 48] = [
+        Self::Down,
+        Self::Up,
+        Self::Top,
+        Self::Bottom,
+        Self::PageDown,
+        Self::PageUp,
+        Self::HalfDown,
+        Self::HalfUp,
+        Self::Focus,
+        Self::Open,
+        Self::Back,
+        Self::Narrow,
+        Self::Widen,
+        Self::Source,
+        Self::SourceDown,
+        Self::SourceUp,
+        Self::Glossary,
+        Self::Files,
+        Self::Compare,
+        Self::Filter,
+        Self::Find,
+        Self::FindNext,
+        Self::FindPrev,
+        Self::ToggleTag,
+        Self::ExcludeTag,
+        Self::TagMode,
+        Self::TagSort,
+        Self::Views,
+        Self::SaveView,
+        Self::DeleteView,
+        Self::Errata,
+        Self::Pending,
+        Self::Fts,
+        Self::Stats,
+        Self::Progress,
+        Self::Group,
+        Self::Yank,
+        Self::Mark,
+        Self::MarkAll,
+        Self::Export,
+        Self::ToggleStatus,
+        Self::AddTag,
+        Self::RemoveTag,
+        Self::EditText,
+        Self::Undo,
+        Self::Palette,
+        Self::Help,
+        Self::Quit,
+    ];
+
+    /// Name in `keymap.json`, default keys, and help text.
+    fn spec(self) -> (&'static str, &'static [&'static str], &'static str) {
+        match self {
+            Self::Down => ("down", &["j", "down"], "move down in the focused pane"),
+            Self::Up => ("up", &["k", "up"], "move up in the focused pane"),
+            Self::Top => ("top", &["g g", "home"], "jump to the first row"),
+            Self::Bottom => ("bottom", &["G", "end"], "jump to the last row"),
+            Self::PageDown => ("page_down", &["pagedown", "ctrl-f"], "page down"),
+            Self::PageUp => ("page_up", &["pageup", "ctrl-b"], "page up"),
+            Self::HalfDown => ("half_down", &["ctrl-d"], "half a page down"),
+            Self::HalfUp => ("half_up", &["ctrl-u"], "half a page up"),
+            Self::Focus => ("focus", &["tab", "backtab"], "switch focus: left ↔ notes"),
+            Self::Open => ("open", &["enter"], "open detail  /  fold a file header"),
+            Self::Back => ("back", &["esc"], "close  /  clear results  /  back"),
+            Self::Narrow => ("narrow", &["<"], "narrow the left pane"),
+            Self::Widen => ("widen", &[">"], "widen the left pane"),
+            Self::Source => ("source", &["c"], "source around the note"),
+            Self::SourceDown => ("source_down", &["J"], "scroll the source view down"),
+            Self::SourceUp => ("source_up", &["K"], "scroll the source view up"),
+            Self::Glossary => ("glossary", &["t"], "toggle tags ↔ glossary (terms)"),
+            Self::Files => ("files", &["d"], "toggle the files tree"),
+            Self::Compare => (
+                "compare",
+                &["D"],
+                "a term's definitions side by side (glossary)",
+            ),
+            Self::Filter => ("filter", &["/"], "filter the left list"),
+            Self::Find => ("find", &["F"], "find in the notes list as you type"),
+            Self::FindNext => ("find_next", &["n"], "next match of the find"),
+            Self::FindPrev => ("find_prev", &["N"], "previous match of the find"),
+            Self::ToggleTag => ("toggle_tag", &["space"], "toggle a tag pick (tags)"),
+            Self::ExcludeTag => ("exclude_tag", &["!"], "exclude a tag's notes: [-] (tags)"),
+            Self::TagMode => ("tag_mode", &["o"], "picked tags: match all ↔ any"),
+            Self::TagSort => ("tag_sort", &["S"], "sort tags: name ↔ note count"),
+            Self::Views => ("views", &["v"], "saved views: pick one to apply"),
+            Self::SaveView => ("save_view", &["V"], "save the current view"),
+            Self::DeleteView => (
+                "delete_view",
+                &["delete"],
+                "delete the highlighted saved view (views list)",
+            ),
+            Self::Errata => ("errata", &["e"], "errata — show FIXMEs"),
+            Self::Pending => ("pending", &["p"], "pending — show TODOs"),
+            Self::Fts => ("fts", &["f"], "full-text search"),
+            Self::Stats => ("stats", &["s"], "stats panel"),
+            Self::Progress => ("progress", &["P"], "progress chart"),
+            Self::Group => ("group", &["z"], "group notes by file"),
+            Self::Yank => (
+                "yank",
+                &["y"],
+                "yank the marked notes (or the selected one)",
+            ),
+            Self::Mark => ("mark", &["m"], "mark / unmark the selected note"),
+            Self::MarkAll => ("mark_all", &["M"], "mark every listed note (again: unmark)"),
+            Self::Export => (
+                "export",
+                &["Y"],
+                "export marked notes: text, markdown, json",
+            ),
+            Self::ToggleStatus => ("toggle_status", &["x"], "mark FIXME / TODO done (or open)"),
+            Self::AddTag => ("add_tag", &["+"], "add a tag"),
+            Self::RemoveTag => ("remove_tag", &["-"], "remove a tag"),
+            Self::EditText => ("edit_text", &["i"], "edit the note text"),
+            Self::Undo => ("undo", &["u"], "undo the last edit"),
+            Self::Palette => ("palette", &[":"], "command palette: run any action"),
+            Self::Help => ("help", &["h", "?"], "this help"),
+            Self::Quit => ("quit", &["q"], "quit"),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        self.spec().0
+    }
+
+    pub fn description(self) -> &'static str {
+        self.spec().2
+    }
+}
+
+impl FromStr for Action {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|a| a.name() == s)
+            .ok_or_else(|| Error::Config(format!("keymap: unknown action {s:?}")))
+    }
+}
+
+/// A key with its Ctrl / Alt modifiers; Shift is part of the character.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Key {
+    code: KeyCode,
+    ctrl: bool,
+    alt: bool,
+}
+
+impl Key {
+    pub fn from_event(event: &KeyEvent) -> Self {
+        Self {
+            code: event.code,
+            ctrl: event.modifiers.contains(KeyModifiers::CONTROL),
+            alt: event.modifiers.contains(KeyModifiers::ALT),
+        }
+    }
+}
+
+const NAMED: [(&str, KeyCode); 15] = [
+    ("enter", KeyCode::Enter),
+    ("esc", KeyCode::Esc),
+    ("tab", KeyCode::Tab),
+    ("backtab", KeyCode::BackTab),
+    ("space", KeyCode::Char(' ')),
+    ("backspace", KeyCode::Backspace),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("delete", KeyCode::Delete),
+];
+
+impl FromStr for Key {
+    type Err = Error;
+
+    /// `j`, `J`, `?`, `enter`, `space`, `ctrl-n`, `alt-x`, `f5`, ...
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mut ctrl, mut alt) = (false, false);
+        let mut rest = s;
+        loop {
+            if let Some(r) = rest.strip_prefix("ctrl-").filter(|r| !r.is_empty()) {
+                (ctrl, rest) = (true, r);
+            } else if let Some(r) = rest.strip_prefix("alt-").filter(|r| !r.is_empty()) {
+                (alt, rest) = (true, r);
+            } else {
+                break;
+            }
+        }
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match NAMED.iter().find(|(name, _)| *name == rest) {
+                Some((_, code)) => *code,
+                None => match rest.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(Error::Config(format!("keymap: unknown key {s:?}"))),
+                },
+            },
+        };
+        Ok(Self { code, ctrl, alt })
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            f.write_str("ctrl-")?;
+        }
+        if self.alt {
+            f.write_str("alt-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::Up => f.write_str("↑"),
+            KeyCode::Down => f.write_str("↓"),
+            KeyCode::BackTab => f.write_str("shift-tab"),
+            KeyCode::F(n) => write!(f, "f{n}"),
+            code => match NAMED.iter().find(|(_, c)| *c == code) {
+                Some((name, _)) => f.write_str(name),
+                None => write!(f, "{code:?}"),
+            },
+        }
+    }
+}
+
+/// One key, or a sequence like `g g` pressed one after the other.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Binding(Vec<Key>);
+
+impl FromStr for Binding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let keys = s
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<crate::Result<Vec<Key>>>()?;
+        if keys.is_empty() {
+            return Err(Error::Config(format!("keymap: unknown key {s:?}")));
+        }
+        Ok(Self(keys))
+    }
+}
+
+impl fmt::Display for Binding {
+    /// `gg` for character sequences, `ctrl-x ctrl-s` otherwise.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keys: Vec<String> = self.0.iter().map(Key::to_string).collect();
+        let separator = if keys.iter().all(|k| k.chars().count() == 1) {
+            ""
+        } else {
+            " "
+        };
+        f.write_str(&keys.join(separator))
+    }
+}
+
+/// What the keys pressed so far mean.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lookup {
+    Action(Action),
+    /// The start of a longer binding: wait for the next key.
+    Prefix,
+    None,
+}
+
+/// `keymap.json` in the config dir: action name → keys. Listed actions
+/// replace their default keys and take those keys from other actions.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct KeymapFile {
+    #[serde(default)]
+    bindings: BTreeMap<String, Vec<String>>,
+}
+
+/// Active TUI key bindings.
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    actions: HashMap<Binding, Action>,
+    keys: HashMap<Action, Vec<Binding>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::from_file(KeymapFile::default()).expect("default keymap has no conflicts")
+    }
+}
+
+impl Keymap {
+    pub fn load() -> crate::Result<Self> {
+        match configuration::provider(FILENAME)?.load::<KeymapFile>() {
+            Ok(file) => Self::from_file(file),
+            Err(abseil::Error::NotFound) => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Where `keymap.json` is read from (it need not exist).
+    pub fn path() -> crate::Result<PathBuf> {
+        let provider = configuration::provider(FILENAME)?;
+        Ok(provider.location().path().join(FILENAME))
+    }
+
+    fn from_file(file: KeymapFile) -> crate::Result<Self> {
+        let mut custom = HashMap::new();
+        for (name, keys) in &file.bindings {
+            let action: Action = name.parse()?;
+            let keys = keys
+                .iter()
+                .map(|k| k.parse())
+                .collect::<crate::Result<Vec<Binding>>>()?;
+            custom.insert(action, keys);
+        }
+
+        let mut actions = HashMap::new();
+        // Custom bindings first, so a clash between two of them is an error
+        // while a default that reuses a custom key just loses it.
+        for (action, keys) in &custom {
+            for key in keys {
+                if let Some(other) = actions.insert(key.clone(), *action) {
+                    if other != *action {
+                        let (a, b) = sorted_names(other, *action);
+                        return Err(Error::Config(format!(
+                            "keymap: {key} is bound to both {a} and {b}"
+                        )));
+                    }
+                }
+            }
+        }
+        let mut keys = HashMap::new();
+        for action in Action::ALL {
+            let bound = match custom.remove(&action) {
+                Some(bound) => bound,
+                None => action
+                    .spec()
+                    .1
+                    .iter()
+                    .map(|k| k.parse().expect("default key parses"))
+                    .filter(|k| !actions.contains_key(k))
+                    .collect(),
+            };
+            for key in &bound {
+                actions.entry(key.clone()).or_insert(action);
+            }
+            keys.insert(action, bound);
+        }
+        Ok(Self { actions, keys })
+    }
+
+    /// Keys pressed so far: a whole binding, the start of a longer one
+    /// (which wins, so `g` waits to see if `g g` follows), or nothing.
+    pub fn lookup(&self, keys: &[Key]) -> Lookup {
+        let longer = self
+            .actions
+            .keys()
+            .any(|b| b.0.len() > keys.len() && b.0.starts_with(keys));
+        match self.exact(keys) {
+            _ if longer => Lookup::Prefix,
+            Some(action) => Lookup::Action(action),
+            None => Lookup::None,
+        }
+    }
+
+    /// The action bound to exactly `keys`.
+    pub fn exact(&self, keys: &[Key]) -> Option<Action> {
+        self.actions.get(&Binding(keys.to_vec())).copied()
+    }
+
+    /// First key for `action`, for status-bar hints.
+    pub fn key(&self, action: Action) -> String {
+        match self.keys.get(&action).and_then(|keys| keys.first()) {
+            Some(key) => key.to_string(),
+            None => "—".into(),
+        }
+    }
+
+    /// Keys for `action` as shown in help, e.g. `j / ↓`.
+    pub fn keys(&self, action: Action) -> String {
+        let keys = self.keys.get(&action).map(Vec::as_slice).unwrap_or(&[]);
+        if keys.is_empty() {
+            return "—".into();
+        }
+        keys.iter()
+            .map(Binding::to_string)
+            .collect::<Vec<_>>()
+            .join(" / ")
+    }
+}
+
+fn sorted_names(a: Action, b: Action) -> (&'static str, &'static str) {
+    if a.name() <= b.name() {
+        (a.name(), b.name())
+    } else {
+        (b.name(), a.name())
+    }
+}
+
+#[cfg(test)]
+impl Keymap {
+    /// The defaults with `bindings` applied, as if read from `keymap.json`.
+    pub(crate) fn with_bindings(bindings: &[(&str, &[&str])]) -> crate::Result<Self> {
+        Self::from_file(KeymapFile {
+            bindings: bindings
+                .iter()
+                .map(|(a, keys)| (a.to_string(), keys.iter().map(|k| k.to_string()).collect()))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keymap(bindings: &[(&str, &[&str])]) -> crate::Result<Keymap> {
+        Keymap::with_bindings(bindings)
+    }
+
+    fn press(code: KeyCode, modifiers: KeyModifiers) -> Key {
+        Key::from_event(&KeyEvent::new(code, modifiers))
+    }
+
+    fn char(c: char) -> Key {
+        press(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn defaults_cover_every_action() {
+        let keymap = Keymap::default();
+        for action in Action::ALL {
+            assert_ne!(keymap.keys(action), "—", "{} has no key", action.name());
+        }
+        assert_eq!(keymap.keys(Action::Down), "j / ↓");
+        let shifted = press(KeyCode::Char('P'), KeyModifiers::SHIFT);
+        assert_eq!(keymap.exact(&[shifted]), Some(Action::Progress));
+    }
+
+    #[test]
+    fn custom_keys_replace_defaults_and_take_them_over() {
+        let keymap = keymap(&[("down", &["ctrl-n"]), ("yank", &["d"])]).unwrap();
+        let ctrl_n = press(KeyCode::Char('n'), KeyModifiers::CONTROL);
+        assert_eq!(keymap.exact(&[ctrl_n]), Some(Action::Down));
+        assert_eq!(keymap.exact(&[char('j')]), None);
+        assert_eq!(keymap.exact(&[char('d')]), Some(Action::Yank));
+        assert_eq!(keymap.keys(Action::Files), "—");
+        assert_eq!(keymap.keys(Action::Down), "ctrl-n");
+    }
+
+    #[test]
+    fn rejects_conflicts_and_unknown_names() {
+        let err = keymap(&[("yank", &["ctrl-y"]), ("undo", &["ctrl-y"])]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "keymap: ctrl-y is bound to both undo and yank"
+        );
+        let err = keymap(&[("yonk", &["y"])]).unwrap_err();
+        assert!(err.to_string().contains("unknown action \"yonk\""));
+        let err = keymap(&[("yank", &["hyper-y"])]).unwrap_err();
+        assert!(err.to_string().contains("unknown key"));
+    }
+
+    #[test]
+    fn parses_and_prints_keys() {
+        for name in ["j", "?", "space", "enter", "ctrl-alt-x", "f5", "pagedown"] {
+            assert_eq!(name.parse::<Key>().unwrap().to_string(), name);
+        }
+        assert_eq!("-".parse::<Key>().unwrap().to_string(), "-");
+        assert_eq!("ctrl--".parse::<Key>().unwrap().to_string(), "ctrl--");
+    }
+
+    #[test]
+    fn sequences_wait_for_their_next_key() {
+        let keymap = keymap(&[
+            ("top", &["g g"]),
+            ("glossary", &["g"]),
+            ("files", &["ctrl-x ctrl-f"]),
+        ])
+        .unwrap();
+        assert_eq!(keymap.lookup(&[char('g')]), Lookup::Prefix);
+        assert_eq!(keymap.exact(&[char('g')]), Some(Action::Glossary));
+        assert_eq!(
+            keymap.lookup(&[char('g'), char('g')]),
+            Lookup::Action(Action::Top)
+        );
+        assert_eq!(keymap.lookup(&[char('g'), char('j')]), Lookup::None);
+        assert_eq!(keymap.lookup(&[char('j')]), Lookup::Action(Action::Down));
+        assert_eq!(keymap.keys(Action::Top), "gg");
+        assert_eq!(keymap.keys(Action::Files), "ctrl-x ctrl-f");
+        // By default `g` only starts `g g`, so no single key waits.
+        let defaults = Keymap::default();
+        assert_eq!(defaults.lookup(&[char('g')]), Lookup::Prefix);
+        assert_eq!(defaults.exact(&[char('g')]), None);
+        assert_eq!(defaults.keys(Action::Top), "gg / home");
+        assert_eq!(
+            defaults.lookup(&[char('t')]),
+            Lookup::Action(Action::Glossary)
+        );
+    }
+}
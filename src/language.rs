@@ -0,0 +1,160 @@
+use std::{fmt, path::Path, str::FromStr};
+
+use glob::Pattern;
+use rust_stemmers::Algorithm;
+use serde::{Deserialize, Serialize};
+
+use crate::configuration::DirConfig;
+
+/// Manuscript language, used for FTS analysis and hyphenation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Language {
+    #[default]
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "de")]
+    German,
+    #[serde(rename = "fr")]
+    French,
+    #[serde(rename = "es")]
+    Spanish,
+    #[serde(rename = "it")]
+    Italian,
+    #[serde(rename = "nl")]
+    Dutch,
+    #[serde(rename = "pt")]
+    Portuguese,
+}
+
+impl Language {
+    pub const ALL: [Language; 7] = [
+        Language::English,
+        Language::German,
+        Language::French,
+        Language::Spanish,
+        Language::Italian,
+        Language::Dutch,
+        Language::Portuguese,
+    ];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+            Language::French => "fr",
+            Language::Spanish => "es",
+            Language::Italian => "it",
+            Language::Dutch => "nl",
+            Language::Portuguese => "pt",
+        }
+    }
+
+    /// Embedded hyphenation dictionary. Only English is built in; the rest
+    /// need the `hyphenate-all` cargo feature.
+    pub fn hyphenation(self) -> Option<hyphenation::Language> {
+        match self {
+            Language::English => Some(hyphenation::Language::EnglishUS),
+            #[cfg(feature = "hyphenate-all")]
+            Language::German => Some(hyphenation::Language::German1996),
+            #[cfg(feature = "hyphenate-all")]
+            Language::French => Some(hyphenation::Language::French),
+            #[cfg(feature = "hyphenate-all")]
+            Language::Spanish => Some(hyphenation::Language::Spanish),
+            #[cfg(feature = "hyphenate-all")]
+            Language::Italian => Some(hyphenation::Language::Italian),
+            #[cfg(feature = "hyphenate-all")]
+            Language::Dutch => Some(hyphenation::Language::Dutch),
+            #[cfg(feature = "hyphenate-all")]
+            Language::Portuguese => Some(hyphenation::Language::Portuguese),
+            #[cfg(not(feature = "hyphenate-all"))]
+            _ => None,
+        }
+    }
+
+    pub fn stemmer(self) -> Algorithm {
+        match self {
+            Language::English => Algorithm::English,
+            Language::German => Algorithm::German,
+            Language::French => Algorithm::French,
+            Language::Spanish => Algorithm::Spanish,
+            Language::Italian => Algorithm::Italian,
+            Language::Dutch => Algorithm::Dutch,
+            Language::Portuguese => Algorithm::Portuguese,
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        let lang = match s.as_str() {
+            "en" | "en-us" | "english" => Language::English,
+            "de" | "german" | "deutsch" => Language::German,
+            "fr" | "french" | "français" | "francais" => Language::French,
+            "es" | "spanish" | "español" | "espanol" => Language::Spanish,
+            "it" | "italian" | "italiano" => Language::Italian,
+            "nl" | "dutch" | "nederlands" => Language::Dutch,
+            "pt" | "portuguese" | "português" | "portugues" => Language::Portuguese,
+            _ => {
+                let codes: Vec<_> = Language::ALL.iter().map(|l| l.code()).collect();
+                return Err(format!(
+                    "unknown language {s:?} (expected one of {})",
+                    codes.join(", ")
+                ));
+            }
+        };
+        Ok(lang)
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// Project default language plus per-file glob overrides.
+#[derive(Clone, Debug, Default)]
+pub struct LanguageMap {
+    default: Language,
+    files: Vec<(Pattern, Language)>,
+}
+
+impl LanguageMap {
+    pub fn new(config: &DirConfig) -> crate::Result<Self> {
+        let mut files = Vec::new();
+        for (glob, &lang) in &config.languages {
+            files.push((Pattern::new(glob)?, lang));
+        }
+        Ok(Self {
+            default: config.language,
+            files,
+        })
+    }
+
+    pub fn default_language(&self) -> Language {
+        self.default
+    }
+
+    /// Language for `path`: the first matching per-file glob, else the default.
+    pub fn language_for(&self, path: &Path) -> Language {
+        self.files
+            .iter()
+            .find(|(pattern, _)| pattern.matches_path(path))
+            .map(|&(_, lang)| lang)
+            .unwrap_or(self.default)
+    }
+
+    /// Every configured language, default first.
+    pub fn languages(&self) -> Vec<Language> {
+        let mut langs = vec![self.default];
+        for &(_, lang) in &self.files {
+            if !langs.contains(&lang) {
+                langs.push(lang);
+            }
+        }
+        langs
+    }
+}
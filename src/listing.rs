@@ -0,0 +1,221 @@
+use std::{cmp::Ordering, collections::HashMap, fs, path::Path, time::SystemTime};
+
+use clap::ValueEnum;
+
+use crate::note::{Kind, Note};
+use crate::store::normalize_tag;
+
+/// Order for listing commands (`--sort`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SortKey {
+    Path,
+    Line,
+    Kind,
+    Tag,
+    Term,
+    /// File modification time, oldest first.
+    Mtime,
+}
+
+/// Sections for listing commands (`--group-by`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    File,
+    Tag,
+    Kind,
+}
+
+/// One `--group-by` section: its header and notes, in listing order.
+pub struct Group<'a> {
+    pub title: String,
+    pub notes: Vec<&'a Note>,
+}
+
+/// Stable sort, so ties keep scan order.
+pub fn sort(notes: &mut [&Note], key: SortKey) {
+    match key {
+        SortKey::Path => notes.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line))),
+        SortKey::Line => notes.sort_by_key(|n| n.line),
+        SortKey::Kind => notes.sort_by_key(|n| kind_rank(&n.kind)),
+        SortKey::Tag => notes.sort_by(|a, b| last_if_none(first_tag(a), first_tag(b))),
+        SortKey::Term => notes.sort_by(|a, b| last_if_none(term_key(a), term_key(b))),
+        SortKey::Mtime => {
+            let mut mtimes: HashMap<&Path, SystemTime> = HashMap::new();
+            for note in notes.iter() {
+                mtimes
+                    .entry(&note.path)
+                    .or_insert_with(|| mtime(&note.path));
+            }
+            notes.sort_by_key(|n| mtimes[n.path.as_path()]);
+        }
+    }
+}
+
+/// Split `notes` into sections, ordered by first appearance. Under
+/// [`GroupBy::Tag`] a note is listed once per tag, matched case- and
+/// `#`-insensitively like tag search and titled with the first spelling
+/// seen; untagged notes come last.
+pub fn group<'a>(notes: &[&'a Note], by: GroupBy) -> Vec<Group<'a>> {
+    let mut groups: Vec<Group<'a>> = Vec::new();
+    let mut keys: Vec<String> = Vec::new();
+    let mut untagged = Vec::new();
+    for &note in notes {
+        let titles = match by {
+            GroupBy::File => vec![note.path.display().to_string()],
+            GroupBy::Kind => vec![kind_title(&note.kind).to_string()],
+            GroupBy::Tag if note.tags.is_empty() => {
+                untagged.push(note);
+                continue;
+            }
+            GroupBy::Tag => note.tags.iter().map(|t| format!("#{t}")).collect(),
+        };
+        let mut seen = Vec::new();
+        for title in titles {
+            let key = match by {
+                GroupBy::Tag => normalize_tag(&title),
+                _ => title.clone(),
+            };
+            if seen.contains(&key) {
+                continue;
+            }
+            match keys.iter().position(|k| *k == key) {
+                Some(i) => groups[i].notes.push(note),
+                None => {
+                    groups.push(Group {
+                        title,
+                        notes: vec![note],
+                    });
+                    keys.push(key.clone());
+                }
+            }
+            seen.push(key);
+        }
+    }
+    if !untagged.is_empty() {
+        groups.push(Group {
+            title: "(untagged)".into(),
+            notes: untagged,
+        });
+    }
+    groups
+}
+
+fn kind_rank(kind: &Kind) -> u8 {
+    match kind {
+        Kind::Fixme => 0,
+        Kind::Todo => 1,
+        Kind::Define { .. } => 2,
+        Kind::Note => 3,
+    }
+}
+
+fn kind_title(kind: &Kind) -> &'static str {
+    match kind {
+        Kind::Fixme => "FIXME",
+        Kind::Todo => "TODO",
+        Kind::Define { .. } => "Definitions",
+        Kind::Note => "Notes",
+    }
+}
+
+fn first_tag(note: &Note) -> Option<String> {
+    note.tags.iter().map(|t| t.to_lowercase()).min()
+}
+
+fn term_key(note: &Note) -> Option<String> {
+    match &note.kind {
+        Kind::Define { term } => Some(term.to_lowercase()),
+        _ => None,
+    }
+}
+
+/// Ascending, with `None` after every value.
+fn last_if_none<T: Ord>(a: Option<T>, b: Option<T>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn mtime(path: &Path) -> SystemTime {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::Parser;
+
+    fn notes() -> Vec<Note> {
+        let parser = Parser::new();
+        let mut notes = parser.parse_file(
+            Path::new("ch02.md"),
+            "<!-- NOTE the knives march. #army -->\n\
+             <!-- FIXME tax rate #plot #army -->\n",
+        );
+        notes.extend(parser.parse_file(
+            Path::new("ch01.md"),
+            "\n\n<!-- NOTE def spearsheaves a tax. -->\n\
+             <!-- TODO describe the cavalry -->\n",
+        ));
+        notes
+    }
+
+    fn texts(notes: &[&Note]) -> Vec<String> {
+        notes.iter().map(|n| n.text.clone()).collect()
+    }
+
+    #[test]
+    fn sorts_by_path_kind_and_tag() {
+        let all = notes();
+        let mut notes: Vec<_> = all.iter().collect();
+
+        sort(&mut notes, SortKey::Path);
+        assert_eq!(notes[0].path, Path::new("ch01.md"));
+        assert_eq!(notes[3].text, "tax rate #plot #army");
+
+        sort(&mut notes, SortKey::Kind);
+        assert!(notes[0].is_fixme() && notes[1].is_todo());
+
+        sort(&mut notes, SortKey::Tag);
+        assert_eq!(
+            texts(&notes[..2]),
+            ["tax rate #plot #army", "the knives march. #army"]
+        );
+        assert!(notes[2].tags.is_empty() && notes[3].tags.is_empty());
+    }
+
+    #[test]
+    fn groups_by_file_and_tag() {
+        let all = notes();
+        let notes: Vec<_> = all.iter().collect();
+
+        let files = group(&notes, GroupBy::File);
+        let titles: Vec<_> = files.iter().map(|g| g.title.as_str()).collect();
+        assert_eq!(titles, ["ch02.md", "ch01.md"]);
+        assert_eq!(files[0].notes.len(), 2);
+
+        let tags = group(&notes, GroupBy::Tag);
+        let titles: Vec<_> = tags.iter().map(|g| g.title.as_str()).collect();
+        assert_eq!(titles, ["#army", "#plot", "(untagged)"]);
+        assert_eq!(tags[0].notes.len(), 2);
+        assert_eq!(tags[2].notes.len(), 2);
+    }
+
+    #[test]
+    fn tag_groups_ignore_case() {
+        let notes = Parser::new().parse_file(
+            Path::new("ch01.md"),
+            "<!-- NOTE one #Army -->\n<!-- NOTE two #army #ARMY -->\n",
+        );
+        let notes: Vec<_> = notes.iter().collect();
+        let tags = group(&notes, GroupBy::Tag);
+        let titles: Vec<_> = tags.iter().map(|g| g.title.as_str()).collect();
+        assert_eq!(titles, ["#Army"]);
+        assert_eq!(texts(&tags[0].notes), ["one #Army", "two #army #ARMY"]);
+    }
+}
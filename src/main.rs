@@ -1,19 +1,52 @@
 mod cli;
 mod configuration;
+mod context;
+mod date;
+mod diff;
 mod error;
+mod export;
 mod format;
+mod git;
+mod keymap;
+mod language;
+mod listing;
 mod logging;
 mod note;
+mod pager;
+mod progress;
 mod search;
+mod session;
+mod stats;
 mod store;
+mod theme;
 mod tui;
+mod view;
+mod writeback;
 
-use std::{io, process};
+use std::{
+    env,
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+    process,
+};
 
-use cli::{Args, Command, Config, Define, Search};
+use cli::{
+    Args, ColorChoice, Command, Config, Define, DiffArgs, Listing, Output, OutputFormat,
+    ProgressArgs, Scope, Search, StatsArgs, ViewArgs, Width, Worklist,
+};
+use configuration::DirConfig;
+use error::Error;
 use format::Formatter;
-use search::FtsIndex;
+use git::{History, HistoryFilter};
+use keymap::Keymap;
+use language::LanguageMap;
+use note::Note;
+use pager::Pager;
+use search::{Analyzer, FtsIndex};
+use stats::Stats;
 use store::NoteStore;
+use textwrap::termwidth;
+use theme::Theme;
 
 pub type Result<T, E = error::Error> = std::result::Result<T, E>;
 
@@ -26,57 +59,175 @@ fn main() {
 }
 
 fn run(args: Args) -> Result<()> {
+    let output = &args.output;
     match args.command {
-        None | Some(Command::Tui) => cmd_tui(),
+        None => cmd_tui(Scope::default(), output),
+        Some(Command::Tui(scope)) => cmd_tui(scope, output),
         Some(Command::Config(cmd)) => cmd_config(cmd),
-        Some(Command::Define(cmd)) => cmd_define(cmd),
-        Some(Command::Search(cmd)) => cmd_search(cmd),
-        Some(Command::Errata) => cmd_errata(),
-        Some(Command::Pending) => cmd_pending(),
-        Some(Command::Glossary) => cmd_glossary(),
-        Some(Command::All) => cmd_all(),
+        Some(Command::Define(cmd)) => cmd_define(cmd, output),
+        Some(Command::Search(cmd)) => cmd_search(cmd, output),
+        Some(Command::Errata(cmd)) => cmd_errata(cmd, output),
+        Some(Command::Pending(cmd)) => cmd_pending(cmd, output),
+        Some(Command::Glossary) => cmd_glossary(output),
+        Some(Command::All(cmd)) => cmd_all(cmd, output),
+        Some(Command::Stats(cmd)) => cmd_stats(cmd, output),
+        Some(Command::Diff(cmd)) => cmd_diff(cmd, output),
+        Some(Command::Snapshot) => cmd_snapshot(),
+        Some(Command::Progress(cmd)) => cmd_progress(cmd),
+        Some(Command::View(cmd)) => cmd_view(cmd, output),
     }
 }
 
-fn load_store() -> Result<NoteStore> {
+fn load_store() -> Result<(DirConfig, NoteStore)> {
     let config = configuration::load_or_prompt()?;
-    NoteStore::load(&config.glob)
+    let store = NoteStore::load(&config.glob)?;
+    Ok((config, store))
+}
+
+/// Load only the files under `scope` that the configured glob also matches.
+fn load_scoped(scope: &Scope) -> Result<(DirConfig, NoteStore)> {
+    let config = configuration::load_or_prompt()?;
+    let paths = scope_paths(&config, scope)?;
+    let store = NoteStore::load_within(&config.glob, &paths)?;
+    Ok((config, store))
+}
+
+/// Scope paths in the form the glob yields: relative to the working
+/// directory, unless the glob itself is absolute.
+fn scope_paths(config: &DirConfig, scope: &Scope) -> Result<Vec<PathBuf>> {
+    let cwd = configuration::current_dir()?;
+    let absolute_glob = Path::new(&config.glob).is_absolute();
+    scope
+        .paths()
+        .map(|path| {
+            if !path.exists() {
+                return Err(Error::Message(format!(
+                    "{}: no such file or directory",
+                    path.display()
+                )));
+            }
+            let path = store::normalize_path(&cwd.join(path));
+            Ok(match path.strip_prefix(&cwd) {
+                Ok(relative) if !absolute_glob => relative.to_path_buf(),
+                _ => path,
+            })
+        })
+        .collect()
+}
+
+fn formatter(config: &DirConfig, output: &Output) -> Result<Formatter> {
+    let formatter =
+        Formatter::new(LanguageMap::new(config)?, !output.no_hyphenate).with_theme(Theme::load()?);
+    let formatter = if use_color(output.color, io::stdout().is_terminal()) {
+        formatter
+    } else {
+        formatter.without_color()
+    };
+    Ok(match output.width {
+        _ if output.no_wrap => formatter.without_wrap(),
+        Some(Width::Auto) => formatter.with_width(termwidth()),
+        Some(Width::Columns(cols)) => formatter.with_width(cols),
+        None => formatter,
+    })
+}
+
+fn pager(config: &DirConfig, output: &Output) -> Pager {
+    Pager::new(config.pager && !output.no_pager)
+}
+
+/// `--color always|never` wins; `auto` needs a terminal and no `NO_COLOR`.
+fn use_color(choice: ColorChoice, terminal: bool) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => terminal && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()),
+    }
 }
 
 fn cmd_config(cmd: Config) -> Result<()> {
-    match cmd.glob {
-        Some(glob) => {
-            let config = configuration::set_glob(glob)?;
-            println!("scan glob set to {:?}", config.glob);
-        }
-        None => match configuration::show_config()? {
+    if let Some(glob) = &cmd.glob {
+        let config = configuration::set_glob(glob)?;
+        println!("scan glob set to {:?}", config.glob);
+    }
+
+    if cmd.language.is_some()
+        || !cmd.file_languages.is_empty()
+        || cmd.fold_accents.is_some()
+        || cmd.pager.is_some()
+    {
+        let file_languages = cmd
+            .file_languages
+            .iter()
+            .map(|raw| configuration::parse_file_language(raw))
+            .collect::<Result<Vec<_>>>()?;
+        let config = configuration::update(|config| {
+            if let Some(language) = cmd.language {
+                config.language = language;
+            }
+            for (glob, language) in file_languages {
+                match language {
+                    Some(language) => config.languages.insert(glob, language),
+                    None => config.languages.remove(&glob),
+                };
+            }
+            if let Some(fold) = cmd.fold_accents {
+                config.fold_accents = fold;
+            }
+            if let Some(pager) = cmd.pager {
+                config.pager = pager;
+            }
+        })?;
+        print_analysis(&config);
+        return Ok(());
+    }
+
+    if cmd.glob.is_none() {
+        match configuration::show_config()? {
             Some((dir, config)) => {
                 println!("{}: {}", dir.display(), config.glob);
+                print_analysis(&config);
+                println!("theme: {}", Theme::path()?.display());
+                println!("keymap: {}", Keymap::path()?.display());
             }
             None => {
                 println!("no config for this directory; run `notes config <glob>`");
             }
-        },
+        }
     }
     Ok(())
 }
 
-fn cmd_define(cmd: Define) -> Result<()> {
-    let store = load_store()?;
+fn print_analysis(config: &DirConfig) {
+    println!("language: {}", config.language);
+    for (glob, language) in &config.languages {
+        println!("  {glob}: {language}");
+    }
+    if config.fold_accents {
+        println!("fold accents: on");
+    }
+    if !config.pager {
+        println!("pager: off");
+    }
+}
+
+fn cmd_define(cmd: Define, output: &Output) -> Result<()> {
+    let (config, store) = load_store()?;
     let notes = store.define(&cmd.term);
     if notes.is_empty() {
         eprintln!("no definition for {:?}", cmd.term);
         return Ok(());
     }
-    Formatter::new().fmt_notes(io::stdout().lock(), &notes)?;
+    let mut out = pager(&config, output);
+    formatter(&config, output)?.fmt_notes(&mut out, &notes)?;
+    out.finish()?;
     Ok(())
 }
 
-fn cmd_search(cmd: Search) -> Result<()> {
-    let store = load_store()?;
+fn cmd_search(cmd: Search, output: &Output) -> Result<()> {
+    let (config, store) = load_scoped(&cmd.scope)?;
     let notes = if cmd.full_text {
-        let fts = FtsIndex::build(&store);
-        fts.search(&store, &cmd.query)
+        let fts = FtsIndex::build(&store, Analyzer::new(&config)?);
+        fts.search(&store, &cmd.query)?
     } else {
         store.search_tag(&cmd.query)
     };
@@ -85,55 +236,223 @@ fn cmd_search(cmd: Search) -> Result<()> {
         eprintln!("no notes matched {:?}", cmd.query);
         return Ok(());
     }
-    Formatter::new().fmt_notes(io::stdout().lock(), &notes)?;
+    let mut out = pager(&config, output);
+    formatter(&config, output)?.fmt_notes(&mut out, &notes)?;
+    out.finish()?;
     Ok(())
 }
 
-fn cmd_errata() -> Result<()> {
-    let store = load_store()?;
-    let notes = store.errata();
-    if notes.is_empty() {
-        eprintln!("no FIXME notes");
+fn cmd_errata(cmd: Worklist, output: &Output) -> Result<()> {
+    let (config, store) = load_scoped(&cmd.listing.scope)?;
+    let Some(notes) = worklist(store.errata(), cmd.all, "FIXME") else {
         return Ok(());
-    }
-    Formatter::new().fmt_notes(io::stdout().lock(), &notes)?;
-    Ok(())
+    };
+    print_listing(&config, output, &cmd.listing, notes)
 }
 
-fn cmd_pending() -> Result<()> {
-    let store = load_store()?;
-    let notes = store.todos();
-    if notes.is_empty() {
-        eprintln!("no TODO notes");
+fn cmd_pending(cmd: Worklist, output: &Output) -> Result<()> {
+    let (config, store) = load_scoped(&cmd.listing.scope)?;
+    let Some(notes) = worklist(store.todos(), cmd.all, "TODO") else {
         return Ok(());
+    };
+    print_listing(&config, output, &cmd.listing, notes)
+}
+
+/// Open items only, unless `all`; `None` (after saying so) when nothing is left.
+fn worklist<'a>(mut notes: Vec<&'a Note>, all: bool, label: &str) -> Option<Vec<&'a Note>> {
+    let total = notes.len();
+    if !all {
+        notes.retain(|n| n.is_open());
     }
-    Formatter::new().fmt_notes(io::stdout().lock(), &notes)?;
-    Ok(())
+    if notes.is_empty() {
+        match total {
+            0 => eprintln!("no {label} notes"),
+            n => eprintln!("no open {label} notes ({n} finished; --all lists them)"),
+        }
+        return None;
+    }
+    Some(notes)
 }
 
-fn cmd_glossary() -> Result<()> {
-    let store = load_store()?;
+fn cmd_glossary(output: &Output) -> Result<()> {
+    let (config, store) = load_store()?;
     let notes = store.glossary();
     if notes.is_empty() {
         eprintln!("no definitions");
         return Ok(());
     }
-    Formatter::new().fmt_glossary(io::stdout().lock(), &notes)?;
+    let mut out = pager(&config, output);
+    formatter(&config, output)?.fmt_glossary(&mut out, &notes)?;
+    out.finish()?;
     Ok(())
 }
 
-fn cmd_all() -> Result<()> {
-    let store = load_store()?;
+fn cmd_all(cmd: Listing, output: &Output) -> Result<()> {
+    let (config, store) = load_scoped(&cmd.scope)?;
     let notes: Vec<_> = store.notes().iter().collect();
     if notes.is_empty() {
         eprintln!("no notes");
         return Ok(());
     }
-    Formatter::new().fmt_notes(io::stdout().lock(), &notes)?;
+    print_listing(&config, output, &cmd, notes)
+}
+
+fn cmd_stats(cmd: StatsArgs, output: &Output) -> Result<()> {
+    let (config, store) = load_scoped(&cmd.scope)?;
+    let stats = Stats::collect(&store, cmd.top);
+    match cmd.format {
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(io::stdout().lock(), &stats)?;
+            println!();
+        }
+        OutputFormat::Text => {
+            let mut out = pager(&config, output);
+            formatter(&config, output)?.fmt_stats(&mut out, &stats)?;
+            out.finish()?;
+        }
+    }
+    Ok(())
+}
+
+fn cmd_diff(cmd: DiffArgs, output: &Output) -> Result<()> {
+    let config = configuration::load_or_prompt()?;
+    let old = diff::load_tree(&cmd.old, &config.glob)?;
+    let new = diff::load_tree(&cmd.new, &config.glob)?;
+    let changes = diff::diff(&old, &new);
+    match cmd.format {
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(io::stdout().lock(), &changes)?;
+            println!();
+        }
+        OutputFormat::Text => {
+            let mut out = pager(&config, output);
+            formatter(&config, output)?.fmt_diff(&mut out, &changes)?;
+            out.finish()?;
+        }
+    }
+    Ok(())
+}
+
+fn cmd_snapshot() -> Result<()> {
+    let (_, store) = load_store()?;
+    let snapshot = progress::Snapshot::take(&store);
+    let line = format!(
+        "{} FIXME, {} TODO, {} tags",
+        snapshot.errata, snapshot.pending, snapshot.tags
+    );
+    let snapshots = progress::record(snapshot)?;
+    println!("snapshot {}: {line}", snapshots.len());
+    Ok(())
+}
+
+fn cmd_progress(cmd: ProgressArgs) -> Result<()> {
+    let snapshots = progress::load()?;
+    match cmd.format {
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(io::stdout().lock(), &snapshots)?;
+            println!();
+        }
+        OutputFormat::Text => {
+            for line in progress::report(&snapshots) {
+                println!("{line}");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cmd_view(cmd: ViewArgs, output: &Output) -> Result<()> {
+    let Some(name) = cmd.name else {
+        let views = view::load()?;
+        if views.is_empty() {
+            eprintln!("no saved views; save one with `V` in the TUI");
+        }
+        let width = views.keys().map(|n| n.chars().count()).max().unwrap_or(0);
+        for (name, view) in &views {
+            println!("{name:<width$}  {}", view.describe());
+        }
+        return Ok(());
+    };
+    let no_view = || {
+        Error::Message(format!(
+            "no saved view named {name:?}; `notes view` lists them"
+        ))
+    };
+    if cmd.delete {
+        if !view::remove(&name)? {
+            return Err(no_view());
+        }
+        println!("deleted view {name}");
+        return Ok(());
+    }
+    let view = view::load()?.remove(&name).ok_or_else(no_view)?;
+    let (config, store) = load_scoped(&cmd.listing.scope)?;
+    let notes = view.notes(&store, Analyzer::new(&config)?)?;
+    if notes.is_empty() {
+        eprintln!("no notes in view {name} ({})", view.describe());
+        return Ok(());
+    }
+    print_listing(&config, output, &cmd.listing, notes)
+}
+
+/// Sort, trim, and section `notes` per the listing flags, then print them.
+fn print_listing(
+    config: &DirConfig,
+    output: &Output,
+    cmd: &Listing,
+    mut notes: Vec<&Note>,
+) -> Result<()> {
+    let filter = HistoryFilter {
+        since: cmd.since,
+        author: cmd.author.clone(),
+        new_since: cmd.new_since.clone(),
+    };
+    if !filter.is_empty() {
+        if let Some(rev) = &filter.new_since {
+            git::verify_rev(rev)?;
+        }
+        let mut history = History::default();
+        let mut kept = Vec::new();
+        for note in notes {
+            if filter.matches(&mut history, note)? {
+                kept.push(note);
+            }
+        }
+        notes = kept;
+        if notes.is_empty() {
+            eprintln!("no notes match the history filters");
+            return Ok(());
+        }
+    }
+    if let Some(key) = cmd.sort {
+        listing::sort(&mut notes, key);
+    }
+    if cmd.reverse {
+        notes.reverse();
+    }
+    if let Some(limit) = cmd.limit {
+        notes.truncate(limit);
+    }
+
+    let formatter = formatter(config, output)?;
+    let mut out = pager(config, output);
+    match cmd.group_by {
+        Some(by) => formatter.fmt_groups(&mut out, &listing::group(&notes, by), by)?,
+        None => formatter.fmt_notes(&mut out, &notes)?,
+    }
+    out.finish()?;
     Ok(())
 }
 
-fn cmd_tui() -> Result<()> {
-    let store = load_store()?;
-    tui::run(store)
+fn cmd_tui(scope: Scope, output: &Output) -> Result<()> {
+    let (config, store) = load_scoped(&scope)?;
+    let formatter = formatter(&config, output)?;
+    let theme = Theme::load()?;
+    let theme = if use_color(output.color, true) {
+        theme
+    } else {
+        theme.without_colors()
+    };
+    let keymap = Keymap::load()?;
+    tui::run(store, Analyzer::new(&config)?, formatter, theme, keymap)
 }
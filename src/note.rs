@@ -1,6 +1,6 @@
 mod parser;
 
-pub use parser::Parser;
+pub use parser::{add_tag, set_status, Parser};
 
 use std::path::PathBuf;
 
@@ -12,11 +12,34 @@ pub enum Kind {
     Define { term: String },
 }
 
+/// Lifecycle of a FIXME or TODO; other kinds are always `Open`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Status {
+    #[default]
+    Open,
+    /// `[x]`, or a `DONE` keyword.
+    Done,
+    /// `WONTFIX`, as a keyword or after FIXME / TODO.
+    WontFix,
+}
+
+impl Status {
+    /// Shown after the kind label; `None` while open.
+    pub fn label(self) -> Option<&'static str> {
+        match self {
+            Self::Open => None,
+            Self::Done => Some("done"),
+            Self::WontFix => Some("won't fix"),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Note {
     pub path: PathBuf,
     pub line: usize,
     pub kind: Kind,
+    pub status: Status,
     pub tags: Vec<String>,
     pub text: String,
 }
@@ -30,6 +53,11 @@ impl Note {
         matches!(self.kind, Kind::Todo)
     }
 
+    /// Still to do: not marked done or won't fix.
+    pub fn is_open(&self) -> bool {
+        self.status == Status::Open
+    }
+
     pub fn search_text(&self) -> String {
         match &self.kind {
             Kind::Define { term } => format!("{term} {}", self.text),
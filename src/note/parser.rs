@@ -1,8 +1,9 @@
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 
 use regex::Regex;
 
-use crate::note::{Kind, Note};
+use crate::note::{Kind, Note, Status};
 
 #[derive(Clone, Debug)]
 pub struct Parser {
@@ -35,14 +36,71 @@ impl Parser {
             .collect()
     }
 
-    fn parse_body(&self, path: PathBuf, line: usize, body: &str) -> Option<Note> {
+    /// Byte range of the comment body `note` was parsed from, checked
+    /// against the note so a file edited since the scan is not clobbered.
+    pub fn locate(&self, source: &str, note: &Note) -> Option<Range<usize>> {
+        self.comment.captures_iter(source).find_map(|cx| {
+            let full = cx.get(0)?;
+            let body = cx.get(1)?;
+            let line = line_number(source, full.start());
+            if line != note.line {
+                return None;
+            }
+            let parsed = self.parse_body(note.path.clone(), line, body.as_str().trim())?;
+            (parsed == *note).then(|| body.range())
+        })
+    }
+
+    /// `text` without `#tag` (matched like the store does: ignoring case).
+    /// A tag in the trailing run of tags is deleted; one inside the prose
+    /// loses only its `#`, so the sentence still reads. `None` when the
+    /// text does not carry the tag.
+    pub fn remove_tag(&self, text: &str, tag: &str) -> Option<String> {
+        let found = self
+            .tags
+            .find_iter(text)
+            .find(|m| tag_name(m.as_str()).eq_ignore_ascii_case(tag))?;
+        let trailing = text[found.end()..]
+            .split_whitespace()
+            .all(|word| word.starts_with('#'));
+        let mut out = text.to_string();
+        if trailing {
+            let start = text[..found.start()].trim_end().len();
+            out.replace_range(start..found.end(), "");
+        } else {
+            out.remove(found.start());
+        }
+        Some(out)
+    }
+
+    /// Whitespace-separated words outside note comments.
+    pub fn prose_words(&self, source: &str) -> usize {
+        self.comment
+            .split(source)
+            .map(|prose| prose.split_whitespace().count())
+            .sum()
+    }
+
+    /// The note in one comment body (the text between `<!--` and `-->`).
+    pub fn parse_body(&self, path: PathBuf, line: usize, body: &str) -> Option<Note> {
         let (kind_label, rest) = split_keyword(body)?;
         let tags = extract_tags(&self.tags, body);
 
+        let mut status = Status::Open;
         let (kind, text) = if eq_ignore_ascii_case(kind_label, "FIXME") {
+            let (marker, _, rest) = split_status(rest);
+            status = marker;
             (Kind::Fixme, rest.trim().to_string())
         } else if eq_ignore_ascii_case(kind_label, "TODO") {
+            let (marker, _, rest) = split_status(rest);
+            status = marker;
             (Kind::Todo, rest.trim().to_string())
+        } else if kind_label == "DONE" {
+            status = Status::Done;
+            (Kind::Todo, rest.trim().to_string())
+        } else if kind_label == "WONTFIX" {
+            status = Status::WontFix;
+            (Kind::Fixme, rest.trim().to_string())
         } else if eq_ignore_ascii_case(kind_label, "NOTE") {
             if let Some(cx) = self.define.captures(rest.trim()) {
                 let term = cx
@@ -69,6 +127,7 @@ impl Parser {
             path,
             line,
             kind,
+            status,
             tags,
             text,
         })
@@ -81,6 +140,58 @@ impl Default for Parser {
     }
 }
 
+/// Rewrite a FIXME / TODO / DONE / WONTFIX comment body to `status`,
+/// keeping the text. A checkbox stays a checkbox (`[ ]` when reopened);
+/// `DONE` and `WONTFIX` keywords become `TODO` and `FIXME` again.
+pub fn set_status(body: &str, status: Status) -> Option<String> {
+    let (keyword, rest) = split_keyword(body)?;
+    let (keyword, checkbox, text) =
+        if eq_ignore_ascii_case(keyword, "FIXME") || eq_ignore_ascii_case(keyword, "TODO") {
+            let (_, checkbox, text) = split_status(rest);
+            (keyword, checkbox, text)
+        } else if keyword == "DONE" {
+            ("TODO", false, rest)
+        } else if keyword == "WONTFIX" {
+            ("FIXME", false, rest)
+        } else {
+            return None;
+        };
+    let marker = match status {
+        Status::Open => checkbox.then_some("[ ]"),
+        Status::Done => Some("[x]"),
+        Status::WontFix => Some("WONTFIX"),
+    };
+    Some(match marker {
+        Some(marker) => format!("{keyword} {marker} {text}"),
+        None => format!("{keyword} {text}"),
+    })
+}
+
+/// `text` with `#tag` appended to its trailing tags.
+pub fn add_tag(text: &str, tag: &str) -> String {
+    format!("{} #{tag}", text.trim_end())
+}
+
+/// A status marker right after FIXME / TODO: the status, whether it was a
+/// checkbox, and the text after it.
+fn split_status(rest: &str) -> (Status, bool, &str) {
+    const MARKERS: [(&str, Status, bool); 5] = [
+        ("[ ]", Status::Open, true),
+        ("[x]", Status::Done, true),
+        ("[X]", Status::Done, true),
+        ("DONE", Status::Done, false),
+        ("WONTFIX", Status::WontFix, false),
+    ];
+    for (marker, status, checkbox) in MARKERS {
+        if let Some(text) = rest.strip_prefix(marker) {
+            if text.is_empty() || text.starts_with(char::is_whitespace) {
+                return (status, checkbox, text.trim_start());
+            }
+        }
+    }
+    (Status::Open, false, rest)
+}
+
 fn split_keyword(body: &str) -> Option<(&str, &str)> {
     let body = body.trim();
     let end = body
@@ -100,11 +211,7 @@ fn extract_tags(pattern: &Regex, text: &str) -> Vec<String> {
     pattern
         .captures_iter(text)
         .filter_map(|cx| {
-            let raw = cx.get(0)?.as_str();
-            let tag = raw
-                .trim_start_matches('#')
-                .trim_end_matches(|u: char| !u.is_ascii_alphanumeric())
-                .trim_end_matches("'s");
+            let tag = tag_name(cx.get(0)?.as_str());
             if tag.is_empty() {
                 None
             } else {
@@ -114,6 +221,13 @@ fn extract_tags(pattern: &Regex, text: &str) -> Vec<String> {
         .collect()
 }
 
+/// The tag in a `#\S+` token: no `#`, trailing punctuation, or possessive.
+fn tag_name(raw: &str) -> &str {
+    raw.trim_start_matches('#')
+        .trim_end_matches(|u: char| !u.is_ascii_alphanumeric())
+        .trim_end_matches("'s")
+}
+
 fn eq_ignore_ascii_case(a: &str, b: &str) -> bool {
     a.eq_ignore_ascii_case(b)
 }
@@ -141,6 +255,12 @@ mod tests {
         notes.into_iter().next().unwrap()
     }
 
+    #[test]
+    fn prose_words_skip_comments() {
+        let source = "One two <!-- NOTE not counted #x -->three\n\nfour.";
+        assert_eq!(Parser::new().prose_words(source), 4);
+    }
+
     #[test]
     fn parses_note_with_tags() {
         let n = parse_one("See Spot. <!-- NOTE Spot is a dog. #character #bio -->\n");
@@ -241,4 +361,94 @@ mod tests {
         let n = parse_one("<!-- NOTE met #Aria's kin, #mothers' pride, and #Bo's. -->");
         assert_eq!(n.tags, vec!["Aria", "mothers", "Bo"]);
     }
+
+    #[test]
+    fn parses_status_markers() {
+        for (source, kind, status, text) in [
+            ("<!-- TODO [ ] map -->", Kind::Todo, Status::Open, "map"),
+            ("<!-- TODO [x] map -->", Kind::Todo, Status::Done, "map"),
+            (
+                "<!-- FIXME [X] dates -->",
+                Kind::Fixme,
+                Status::Done,
+                "dates",
+            ),
+            (
+                "<!-- FIXME WONTFIX dates -->",
+                Kind::Fixme,
+                Status::WontFix,
+                "dates",
+            ),
+            (
+                "<!-- DONE map #draft -->",
+                Kind::Todo,
+                Status::Done,
+                "map #draft",
+            ),
+            (
+                "<!-- WONTFIX dates -->",
+                Kind::Fixme,
+                Status::WontFix,
+                "dates",
+            ),
+            ("<!-- TODO DONEish -->", Kind::Todo, Status::Open, "DONEish"),
+        ] {
+            let n = parse_one(source);
+            assert_eq!((n.kind, n.status, n.text.as_str()), (kind, status, text));
+        }
+        assert!(Parser::new()
+            .parse_file(Path::new("t.md"), "<!-- Done by Bob -->")
+            .is_empty());
+    }
+
+    #[test]
+    fn set_status_keeps_text_and_checkbox_style() {
+        assert_eq!(
+            set_status("TODO map #draft", Status::Done).unwrap(),
+            "TODO [x] map #draft"
+        );
+        assert_eq!(
+            set_status("TODO [x] map", Status::Open).unwrap(),
+            "TODO [ ] map"
+        );
+        assert_eq!(set_status("DONE map", Status::Open).unwrap(), "TODO map");
+        assert_eq!(
+            set_status("fixme [ ] dates", Status::WontFix).unwrap(),
+            "fixme WONTFIX dates"
+        );
+        assert_eq!(set_status("NOTE hi", Status::Done), None);
+    }
+
+    #[test]
+    fn locate_finds_the_comment_body() {
+        let source = "One.\n<!-- NOTE a -->\n<!-- TODO [ ] map -->\n";
+        let parser = Parser::new();
+        let notes = parser.parse_file(Path::new("t.md"), source);
+        let range = parser.locate(source, &notes[1]).unwrap();
+        assert_eq!(&source[range], "TODO [ ] map");
+
+        let mut stale = notes[1].clone();
+        stale.text = "atlas".into();
+        assert_eq!(parser.locate(source, &stale), None);
+    }
+
+    #[test]
+    fn removes_trailing_and_in_text_tags() {
+        let parser = Parser::new();
+        let text = "the #character arc, see #Aria's kin. #draft #Plot";
+        assert_eq!(
+            parser.remove_tag(text, "plot").unwrap(),
+            "the #character arc, see #Aria's kin. #draft"
+        );
+        assert_eq!(
+            parser.remove_tag(text, "character").unwrap(),
+            "the character arc, see #Aria's kin. #draft #Plot"
+        );
+        assert_eq!(
+            parser.remove_tag(text, "aria").unwrap(),
+            "the #character arc, see Aria's kin. #draft #Plot"
+        );
+        assert_eq!(parser.remove_tag(text, "army"), None);
+        assert_eq!(add_tag("map #draft ", "army"), "map #draft #army");
+    }
 }
@@ -0,0 +1,149 @@
+use std::{
+    env,
+    io::{self, IsTerminal, Write},
+    process::{Command, Stdio},
+};
+
+const DEFAULT_PAGER: &str = "less -R";
+
+/// Buffered stdout that goes through `$PAGER` when it would not fit on the
+/// screen, the way git pages its output.
+pub struct Pager {
+    enabled: bool,
+    buf: Vec<u8>,
+}
+
+impl Pager {
+    /// `enabled` is the combined `--no-pager` / config setting; paging also
+    /// needs a terminal on stdout.
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled: enabled && io::stdout().is_terminal(),
+            buf: Vec::new(),
+        }
+    }
+
+    /// Flush the buffered output to the pager, or straight to stdout.
+    pub fn finish(self) -> io::Result<()> {
+        if self.enabled && overflows(&self.buf, screen_size()) {
+            if let Some(command) = pager_command() {
+                match spawn(&command, &self.buf) {
+                    Ok(()) => return Ok(()),
+                    Err(e) => tracing::warn!("pager {command:?} failed: {e}"),
+                }
+            }
+        }
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(&self.buf)?;
+        stdout.flush()
+    }
+}
+
+impl Write for Pager {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.write(data)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// `$PAGER`, else `less -R`, run through `sh -c` as git does. An empty `PAGER` or `cat` disables paging.
+fn pager_command() -> Option<String> {
+    let command = env::var("PAGER").unwrap_or_else(|_| DEFAULT_PAGER.to_string());
+    match command.trim() {
+        "" | "cat" => None,
+        command => Some(command.to_string()),
+    }
+}
+
+/// `(columns, rows)` of the terminal.
+fn screen_size() -> (usize, usize) {
+    crossterm::terminal::size()
+        .map(|(cols, rows)| (cols as usize, rows as usize))
+        .unwrap_or((80, 24))
+}
+
+/// True when `buf` needs more screen rows than the terminal has, counting
+/// lines the terminal soft-wraps (one row is kept for the shell prompt).
+fn overflows(buf: &[u8], (width, height): (usize, usize)) -> bool {
+    let text = String::from_utf8_lossy(buf);
+    let text = text.strip_suffix('\n').unwrap_or(&text);
+    if text.is_empty() {
+        return false;
+    }
+    let rows: usize = text
+        .split('\n')
+        .map(|line| {
+            textwrap::core::display_width(line)
+                .div_ceil(width.max(1))
+                .max(1)
+        })
+        .sum();
+    rows >= height
+}
+
+/// Start `command` and feed it `buf`. Only a failure to start the pager is an
+/// error: once it runs, the output is its to show, so falling back to stdout
+/// would print it twice.
+fn spawn(command: &str, buf: &[u8]) -> io::Result<()> {
+    let mut child = Command::new("sh");
+    child.arg("-c").arg(command).stdin(Stdio::piped());
+    if env::var_os("LESS").is_none() {
+        // Keep colors, and let less exit on its own for short output.
+        child.env("LESS", "FRX");
+    }
+    let mut child = child.spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        match stdin.write_all(buf) {
+            // The user quit the pager before reading everything.
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+            Err(e) => tracing::warn!("writing to pager {command:?} failed: {e}"),
+            Ok(()) => {}
+        }
+    }
+    if let Err(e) = child.wait() {
+        tracing::warn!("waiting for pager {command:?} failed: {e}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_only_output_taller_than_the_screen() {
+        assert!(!overflows(b"", (80, 24)));
+        assert!(!overflows(&b"line\n".repeat(23), (80, 24)));
+        assert!(overflows(&b"line\n".repeat(24), (80, 24)));
+    }
+
+    #[test]
+    fn soft_wrapped_lines_count_toward_the_height() {
+        let long = format!("{}\n", "x".repeat(200));
+        assert!(!overflows(long.as_bytes(), (80, 4)));
+        assert!(overflows(long.repeat(2).as_bytes(), (80, 4)));
+        let colored = format!("\x1b[91m{}\x1b[0m\n", "x".repeat(80));
+        assert!(!overflows(colored.repeat(3).as_bytes(), (80, 4)));
+    }
+
+    #[test]
+    fn pager_runs_through_the_shell() {
+        let out = std::env::temp_dir().join(format!("notes-pager-{}", std::process::id()));
+        let command = format!("cat > '{}' && true", out.display());
+        spawn(&command, b"paged").unwrap();
+        let paged = std::fs::read(&out).unwrap();
+        std::fs::remove_file(&out).unwrap();
+        assert_eq!(paged, b"paged");
+    }
+
+    #[test]
+    fn disabled_pager_is_plain_stdout() {
+        let mut pager = Pager::new(false);
+        write!(pager, "hello").unwrap();
+        assert!(!pager.enabled);
+        assert_eq!(pager.buf, b"hello");
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::configuration;
+use crate::date;
+use crate::store::NoteStore;
+
+const FILENAME: &str = "snapshots.json";
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Counts recorded by `notes snapshot`; finished FIXMEs and TODOs are not
+/// counted, so resolving one shows up as progress.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Snapshot {
+    /// Seconds since the Unix epoch.
+    pub time: i64,
+    pub errata: usize,
+    pub pending: usize,
+    pub tags: usize,
+}
+
+impl Snapshot {
+    pub fn take(store: &NoteStore) -> Self {
+        Self {
+            time: date::now(),
+            errata: store.errata().iter().filter(|n| n.is_open()).count(),
+            pending: store.todos().iter().filter(|n| n.is_open()).count(),
+            tags: store.tags().len(),
+        }
+    }
+}
+
+/// `snapshots.json` in the config dir: canonical project dir → snapshots,
+/// oldest first.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct History {
+    #[serde(default)]
+    directories: HashMap<String, Vec<Snapshot>>,
+}
+
+impl History {
+    fn load() -> crate::Result<Self> {
+        match configuration::provider(FILENAME)?.load::<History>() {
+            Ok(history) => Ok(history),
+            Err(abseil::Error::NotFound) => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// This project's snapshots, oldest first.
+pub fn load() -> crate::Result<Vec<Snapshot>> {
+    let mut history = History::load()?;
+    Ok(history
+        .directories
+        .remove(&configuration::project_key()?)
+        .unwrap_or_default())
+}
+
+/// Append `snapshot` to this project's history, replacing one already
+/// taken the same day (so a git hook and cron can both call it); returns
+/// the full history.
+pub fn record(snapshot: Snapshot) -> crate::Result<Vec<Snapshot>> {
+    let mut history = History::load()?;
+    let snapshots = history
+        .directories
+        .entry(configuration::project_key()?)
+        .or_default();
+    push_daily(snapshots, snapshot);
+    let snapshots = snapshots.clone();
+    configuration::provider(FILENAME)?.store(&history)?;
+    Ok(snapshots)
+}
+
+fn push_daily(snapshots: &mut Vec<Snapshot>, snapshot: Snapshot) {
+    let day = |s: &Snapshot| s.time.div_euclid(86_400);
+    if snapshots
+        .last()
+        .is_some_and(|last| day(last) == day(&snapshot))
+    {
+        snapshots.pop();
+    }
+    snapshots.push(snapshot);
+}
+
+/// One series of the progress report.
+pub struct Series {
+    pub label: &'static str,
+    pub values: Vec<usize>,
+}
+
+impl Series {
+    /// First to last value, e.g. `12 → 2 (-10)`.
+    pub fn change(&self) -> String {
+        let a = self.values.first().copied().unwrap_or(0);
+        let b = self.values.last().copied().unwrap_or(0);
+        format!("{a} → {b} ({:+})", b as i64 - a as i64)
+    }
+}
+
+pub fn series(snapshots: &[Snapshot]) -> [Series; 3] {
+    let values = |f: fn(&Snapshot) -> usize| snapshots.iter().map(f).collect();
+    [
+        Series {
+            label: "FIXME",
+            values: values(|s| s.errata),
+        },
+        Series {
+            label: "TODO",
+            values: values(|s| s.pending),
+        },
+        Series {
+            label: "tags",
+            values: values(|s| s.tags),
+        },
+    ]
+}
+
+/// Bars scaled from zero to the series maximum, so a burn-down reads as
+/// falling bars.
+pub fn sparkline(values: &[usize]) -> String {
+    let max = values.iter().copied().max().unwrap_or(0);
+    values
+        .iter()
+        .map(|&v| {
+            (v * (BARS.len() - 1) + max / 2)
+                .checked_div(max)
+                .map_or(BARS[0], |i| BARS[i])
+        })
+        .collect()
+}
+
+/// `notes progress` text: a sparkline per series with first and last counts.
+pub fn report(snapshots: &[Snapshot]) -> Vec<String> {
+    let (Some(first), Some(last)) = (snapshots.first(), snapshots.last()) else {
+        return vec!["no snapshots yet; run `notes snapshot`".into()];
+    };
+    let mut lines = vec![format!(
+        "{} snapshots, {} → {}",
+        snapshots.len(),
+        date::format_date(first.time),
+        date::format_date(last.time)
+    )];
+    for series in series(snapshots) {
+        lines.push(format!(
+            "{:<6} {}  {}",
+            series.label,
+            sparkline(&series.values),
+            series.change()
+        ));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(errata: usize, pending: usize) -> Snapshot {
+        Snapshot {
+            time: 1_788_220_800,
+            errata,
+            pending,
+            tags: 4,
+        }
+    }
+
+    #[test]
+    fn sparkline_scales_from_zero() {
+        assert_eq!(sparkline(&[8, 4, 0]), "█▅▁");
+        assert_eq!(sparkline(&[0, 0]), "▁▁");
+        assert_eq!(sparkline(&[]), "");
+    }
+
+    #[test]
+    fn report_shows_burn_down() {
+        let lines = report(&[snapshot(12, 3), snapshot(6, 5), snapshot(2, 5)]);
+        assert_eq!(lines[0], "3 snapshots, 2026-09-01 → 2026-09-01");
+        assert_eq!(lines[1], "FIXME  █▅▂  12 → 2 (-10)");
+        assert_eq!(lines[2], "TODO   ▅██  3 → 5 (+2)");
+    }
+
+    #[test]
+    fn one_snapshot_per_day() {
+        let mut snapshots = vec![snapshot(12, 3)];
+        push_daily(&mut snapshots, snapshot(10, 3));
+        assert_eq!(snapshots, [snapshot(10, 3)]);
+        let next_day = Snapshot {
+            time: 1_788_220_800 + 86_400,
+            ..snapshot(9, 3)
+        };
+        push_daily(&mut snapshots, next_day.clone());
+        assert_eq!(snapshots, [snapshot(10, 3), next_day]);
+    }
+}
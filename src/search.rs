@@ -1,38 +1,341 @@
-use memory_indexer::InMemoryIndex;
+mod analyzer;
+mod query;
 
+pub use analyzer::Analyzer;
+
+use analyzer::strip_accents;
+pub use query::Query;
+
+use std::collections::{BTreeSet, HashMap};
+
+use memory_indexer::{InMemoryIndex, SearchMode};
+
+use crate::format::plain_body;
 use crate::note::Note;
 use crate::store::NoteStore;
+use query::{Clause, Field, KindFilter, Term};
 
 const INDEX: &str = "notes";
 
 /// Lazy full-text index over note bodies (and define terms).
+///
+/// Each note is analyzed in its file's language: `stems` answers exact
+/// (stemmed) word lookups, and `index` supplies ranking and a typo-tolerant
+/// fallback over the same stemmed text.
 pub struct FtsIndex {
     index: InMemoryIndex,
+    stems: HashMap<String, BTreeSet<usize>>,
+    analyzer: Analyzer,
 }
 
 impl FtsIndex {
-    pub fn build(store: &NoteStore) -> Self {
+    pub fn build(store: &NoteStore, analyzer: Analyzer) -> Self {
         let mut index = InMemoryIndex::default();
+        let mut stems: HashMap<String, BTreeSet<usize>> = HashMap::new();
         for (id, note) in store.notes().iter().enumerate() {
+            let tokens = note_tokens(&analyzer, note);
             let doc_id = id.to_string();
-            let text = note.search_text();
-            index.add_doc(INDEX, &doc_id, &text, true);
+            index.add_doc(INDEX, &doc_id, &tokens.join(" "), true);
+            for token in tokens {
+                stems.entry(token).or_default().insert(id);
+            }
+        }
+        Self {
+            index,
+            stems,
+            analyzer,
+        }
+    }
+
+    /// Re-index note `id` after an edit changed it from `old` to `new`.
+    pub fn update(&mut self, id: usize, old: &Note, new: &Note) {
+        for token in note_tokens(&self.analyzer, old) {
+            if let Some(ids) = self.stems.get_mut(&token) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    self.stems.remove(&token);
+                }
+            }
+        }
+        let tokens = note_tokens(&self.analyzer, new);
+        self.index
+            .add_doc(INDEX, &id.to_string(), &tokens.join(" "), true);
+        for token in tokens {
+            self.stems.entry(token).or_default().insert(id);
         }
-        Self { index }
     }
 
-    pub fn search_ids(&self, query: &str) -> Vec<usize> {
+    fn search_ids(&self, query: &str) -> Vec<usize> {
+        let tokens = self
+            .analyzer
+            .tokens(query, self.analyzer.languages.default_language());
         self.index
-            .search(INDEX, query)
+            .search_with_mode(INDEX, &tokens.join(" "), SearchMode::Auto)
             .into_iter()
             .filter_map(|(doc_id, _score)| doc_id.parse().ok())
             .collect()
     }
 
-    pub fn search<'a>(&self, store: &'a NoteStore, query: &str) -> Vec<&'a Note> {
-        self.search_ids(query)
+    /// Notes containing `word` under any project language. Without `exact`,
+    /// a word with no stem hits falls back to fuzzy matching.
+    fn word_ids(&self, word: &str, exact: bool) -> BTreeSet<usize> {
+        let mut ids = BTreeSet::new();
+        let mut tokens = Vec::new();
+        for lang in self.analyzer.languages.languages() {
+            let mut hits: Option<BTreeSet<usize>> = None;
+            for token in self.analyzer.tokens(word, lang) {
+                tokens.push(token.clone());
+                let set = self.stems.get(&token).cloned().unwrap_or_default();
+                hits = Some(match hits {
+                    Some(hits) => &hits & &set,
+                    None => set,
+                });
+            }
+            ids.extend(hits.unwrap_or_default());
+        }
+        if ids.is_empty() && !exact {
+            let accented = self.accent_variants(&tokens);
+            ids.extend(
+                self.search_ids(word)
+                    .into_iter()
+                    .filter(|id| !accented.contains(id)),
+            );
+        }
+        ids
+    }
+
+    /// With folding off, notes whose only tie to `tokens` may be a stem
+    /// differing just in accents; the fuzzy index ignores accents, so its
+    /// hits on these are dropped.
+    fn accent_variants(&self, tokens: &[String]) -> BTreeSet<usize> {
+        if self.analyzer.folds_accents() {
+            return BTreeSet::new();
+        }
+        let bare: Vec<String> = tokens.iter().map(|t| strip_accents(t)).collect();
+        self.stems
+            .iter()
+            .filter(|(stem, _)| !tokens.contains(stem) && bare.contains(&strip_accents(stem)))
+            .flat_map(|(_, ids)| ids.iter().copied())
+            .collect()
+    }
+
+    /// Parse and run `query`; hits are ranked by relevance of the free-text
+    /// clauses, then in scan order.
+    pub fn query_ids(&self, store: &NoteStore, query: &str) -> crate::Result<Vec<usize>> {
+        let query = Query::parse(query)?;
+        let mut hits = BTreeSet::new();
+        for group in &query.groups {
+            hits.extend(self.group_ids(store, group));
+        }
+
+        let ranking = query.ranking_text();
+        let mut ids: Vec<usize> = if ranking.is_empty() {
+            Vec::new()
+        } else {
+            self.search_ids(&ranking)
+                .into_iter()
+                .filter(|id| hits.remove(id))
+                .collect()
+        };
+        ids.extend(hits);
+        Ok(ids)
+    }
+
+    pub fn search<'a>(&self, store: &'a NoteStore, query: &str) -> crate::Result<Vec<&'a Note>> {
+        Ok(self
+            .query_ids(store, query)?
             .into_iter()
             .filter_map(|id| store.get(id))
-            .collect()
+            .collect())
+    }
+
+    fn group_ids(&self, store: &NoteStore, group: &[Clause]) -> BTreeSet<usize> {
+        let mut ids: Option<BTreeSet<usize>> = None;
+        for clause in group.iter().filter(|c| !c.negated) {
+            let set = self.term_ids(store, &clause.term, false);
+            ids = Some(match ids {
+                Some(ids) => &ids & &set,
+                None => set,
+            });
+        }
+
+        let mut ids = ids.unwrap_or_else(|| (0..store.notes().len()).collect());
+        for clause in group.iter().filter(|c| c.negated) {
+            for id in self.term_ids(store, &clause.term, true) {
+                ids.remove(&id);
+            }
+        }
+        ids
+    }
+
+    fn term_ids(&self, store: &NoteStore, term: &Term, exact: bool) -> BTreeSet<usize> {
+        match term {
+            Term::Word(word) => self.word_ids(word, exact),
+            Term::Phrase(phrase) => {
+                let phrase = self.analyzer.fold(phrase);
+                matching_ids(store, |n| {
+                    self.analyzer
+                        .fold(&plain_body(&n.search_text()))
+                        .contains(&phrase)
+                })
+            }
+            Term::Field(Field::Tag(tag)) => store.tag_ids(tag).iter().copied().collect(),
+            Term::Field(Field::Term(term)) => store.term_ids(term).iter().copied().collect(),
+            Term::Field(Field::Kind(kind)) => match kind {
+                KindFilter::Fixme => store.fixme_ids().iter().copied().collect(),
+                KindFilter::Todo => store.todo_ids().iter().copied().collect(),
+                KindFilter::Define => store.define_ids().collect(),
+                KindFilter::Note => matching_ids(store, |n| kind.matches(&n.kind)),
+            },
+            Term::Field(Field::Path(path)) => {
+                matching_ids(store, |n| n.path.to_string_lossy().contains(path.as_str()))
+            }
+        }
+    }
+}
+
+fn note_tokens(analyzer: &Analyzer, note: &Note) -> Vec<String> {
+    analyzer.tokens(
+        &note.search_text(),
+        analyzer.languages.language_for(&note.path),
+    )
+}
+
+fn matching_ids(store: &NoteStore, pred: impl Fn(&Note) -> bool) -> BTreeSet<usize> {
+    store
+        .notes()
+        .iter()
+        .enumerate()
+        .filter(|(_, n)| pred(n))
+        .map(|(id, _)| id)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::DirConfig;
+    use crate::language::Language;
+    use crate::note::Parser;
+    use std::path::Path;
+
+    fn store() -> NoteStore {
+        let parser = Parser::new();
+        let mut notes = parser.parse_file(
+            Path::new("ch01.md"),
+            "<!-- NOTE def spearsheaves a tax taken from the crop as it is harvested. -->\n\
+             <!-- NOTE the knives march at dawn. #army -->\n",
+        );
+        notes.extend(parser.parse_file(
+            Path::new("ch04.md"),
+            "<!-- FIXME the tax rate changes between chapters #plot -->\n\
+             <!-- TODO describe the cavalry #army -->\n",
+        ));
+        NoteStore::from_notes(notes)
+    }
+
+    fn texts(store: &NoteStore, query: &str) -> Vec<String> {
+        texts_with(store, Analyzer::default(), query)
+    }
+
+    fn texts_with(store: &NoteStore, analyzer: Analyzer, query: &str) -> Vec<String> {
+        let fts = FtsIndex::build(store, analyzer);
+        let mut texts: Vec<String> = fts
+            .search(store, query)
+            .unwrap()
+            .into_iter()
+            .map(|n| n.text.clone())
+            .collect();
+        texts.sort();
+        texts
+    }
+
+    #[test]
+    fn update_reindexes_an_edited_note() {
+        let mut store = store();
+        let mut fts = FtsIndex::build(&store, Analyzer::default());
+        let old = store.get(1).unwrap().clone();
+        let new = Note {
+            text: "the knives ride at noon. #cavalry".into(),
+            tags: vec!["cavalry".into()],
+            ..old.clone()
+        };
+        store.replace(1, new.clone());
+        fts.update(1, &old, &new);
+        assert!(fts.query_ids(&store, "dawn").unwrap().is_empty());
+        assert_eq!(fts.query_ids(&store, "noon").unwrap(), [1]);
+        assert_eq!(fts.query_ids(&store, "tag:cavalry").unwrap(), [1]);
+        assert_eq!(fts.query_ids(&store, "tag:army").unwrap(), [3]);
+    }
+
+    #[test]
+    fn words_and_exclusion() {
+        let store = store();
+        assert_eq!(texts(&store, "tax").len(), 2);
+        assert_eq!(
+            texts(&store, "tax -harvested"),
+            vec!["the tax rate changes between chapters #plot"]
+        );
+    }
+
+    #[test]
+    fn or_unions_groups() {
+        let store = store();
+        assert_eq!(texts(&store, "dawn OR cavalry").len(), 2);
+    }
+
+    #[test]
+    fn phrase_must_be_contiguous() {
+        let store = store();
+        assert_eq!(texts(&store, r#""knives march""#).len(), 1);
+        assert!(texts(&store, r#""march knives""#).is_empty());
+    }
+
+    #[test]
+    fn field_filters_use_store_indexes() {
+        let store = store();
+        assert_eq!(texts(&store, "tag:army").len(), 2);
+        assert_eq!(
+            texts(&store, "tag:army kind:todo"),
+            vec!["describe the cavalry #army"]
+        );
+        assert_eq!(texts(&store, "term:Spearsheaves").len(), 1);
+        assert_eq!(texts(&store, "path:ch04 -kind:fixme").len(), 1);
+        assert_eq!(texts(&store, "kind:note").len(), 1);
+    }
+
+    #[test]
+    fn stemmed_words_match_inflections() {
+        let store = store();
+        for query in ["harvest", "harvesting", "marched", "chapter"] {
+            assert_eq!(texts(&store, query).len(), 1, "query {query:?}");
+        }
+    }
+
+    #[test]
+    fn per_file_language_and_accent_folding() {
+        let notes = Parser::new().parse_file(
+            Path::new("fr/ch01.md"),
+            "<!-- NOTE les récoltes sont levées à l'aube -->",
+        );
+        let store = NoteStore::from_notes(notes);
+        let mut config = DirConfig::new("**/*.md");
+        config
+            .languages
+            .insert("fr/**".to_string(), Language::French);
+
+        let strict = || Analyzer::new(&config).unwrap();
+        assert_eq!(texts_with(&store, strict(), "récolte").len(), 1);
+        assert_eq!(texts_with(&store, strict(), "-recolte").len(), 1);
+        assert!(texts_with(&store, strict(), "recolte").is_empty());
+        // Typos still fall back to fuzzy matching without folding.
+        let fts = FtsIndex::build(&store, strict());
+        assert!(fts.word_ids("levéés", true).is_empty());
+        assert_eq!(fts.word_ids("levéés", false).len(), 1);
+
+        config.fold_accents = true;
+        let folded = || Analyzer::new(&config).unwrap();
+        assert!(texts_with(&store, folded(), "-recolte").is_empty());
+        assert_eq!(texts_with(&store, folded(), "recolte").len(), 1);
     }
 }
@@ -0,0 +1,126 @@
+use rust_stemmers::Stemmer;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::configuration::DirConfig;
+use crate::language::{Language, LanguageMap};
+
+/// Language-aware tokenizer: lowercases, optionally folds accents, and stems.
+#[derive(Default)]
+pub struct Analyzer {
+    pub languages: LanguageMap,
+    fold_accents: bool,
+    stemmers: Vec<(Language, Stemmer)>,
+}
+
+impl Analyzer {
+    pub fn new(config: &DirConfig) -> crate::Result<Self> {
+        let languages = LanguageMap::new(config)?;
+        let stemmers = languages
+            .languages()
+            .into_iter()
+            .map(|lang| (lang, Stemmer::create(lang.stemmer())))
+            .collect();
+        Ok(Self {
+            languages,
+            fold_accents: config.fold_accents,
+            stemmers,
+        })
+    }
+
+    pub fn folds_accents(&self) -> bool {
+        self.fold_accents
+    }
+
+    /// Lowercase and, when configured, strip diacritics.
+    pub fn fold(&self, text: &str) -> String {
+        let lower = text.to_lowercase();
+        if self.fold_accents {
+            strip_accents(&lower)
+        } else {
+            lower
+        }
+    }
+
+    /// Stemmed tokens of `text` in `lang`.
+    pub fn tokens(&self, text: &str, lang: Language) -> Vec<String> {
+        let created;
+        let stemmer = match self.stemmers.iter().find(|(l, _)| *l == lang) {
+            Some((_, stemmer)) => stemmer,
+            None => {
+                created = Stemmer::create(lang.stemmer());
+                &created
+            }
+        };
+        self.fold(text)
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|t| !t.is_empty())
+            .map(|t| stemmer.stem(t).into_owned())
+            .collect()
+    }
+}
+
+/// `text` without diacritics.
+pub fn strip_accents(text: &str) -> String {
+    text.nfd().filter(|c| !is_combining_mark(*c)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::path::Path;
+
+    fn analyzer(fold_accents: bool) -> Analyzer {
+        let config = DirConfig {
+            language: Language::English,
+            languages: BTreeMap::from([
+                ("de/*.md".to_string(), Language::German),
+                ("fr/*.md".to_string(), Language::French),
+            ]),
+            fold_accents,
+            ..DirConfig::new("**/*.md")
+        };
+        Analyzer::new(&config).unwrap()
+    }
+
+    #[test]
+    fn english_inflections_share_a_stem() {
+        let a = analyzer(false);
+        let stems: Vec<_> = ["harvest", "harvested", "harvesting", "Harvests"]
+            .iter()
+            .map(|w| a.tokens(w, Language::English))
+            .collect();
+        assert!(stems.iter().all(|s| s == &stems[0]), "{stems:?}");
+    }
+
+    #[test]
+    fn german_and_french_stemming() {
+        let a = analyzer(false);
+        assert_eq!(
+            a.tokens("Ernten", Language::German),
+            a.tokens("ernte", Language::German)
+        );
+        assert_eq!(
+            a.tokens("récoltes", Language::French),
+            a.tokens("récolte", Language::French)
+        );
+    }
+
+    #[test]
+    fn per_file_language() {
+        let a = analyzer(false).languages;
+        assert_eq!(a.language_for(Path::new("de/ch01.md")), Language::German);
+        assert_eq!(a.language_for(Path::new("fr/ch01.md")), Language::French);
+        assert_eq!(a.language_for(Path::new("ch01.md")), Language::English);
+        assert_eq!(
+            a.languages(),
+            vec![Language::English, Language::German, Language::French]
+        );
+    }
+
+    #[test]
+    fn accent_folding_is_optional() {
+        assert_eq!(analyzer(false).fold("Café Noël"), "café noël");
+        assert_eq!(analyzer(true).fold("Café Noël"), "cafe noel");
+    }
+}
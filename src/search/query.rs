@@ -0,0 +1,291 @@
+use crate::error::Error;
+use crate::note::Kind;
+
+/// Parsed full-text query: OR-separated groups, each an AND of clauses.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Query {
+    pub groups: Vec<Vec<Clause>>,
+}
+
+/// One query term, optionally negated with a leading `-`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Clause {
+    pub negated: bool,
+    pub term: Term,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Term {
+    /// Bare word, matched through the FTS index.
+    Word(String),
+    /// `"quoted phrase"`, matched as a contiguous run of words.
+    Phrase(String),
+    /// `field:value`, matched against the store indexes.
+    Field(Field),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Field {
+    Term(String),
+    Tag(String),
+    Kind(KindFilter),
+    Path(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KindFilter {
+    Note,
+    Define,
+    Fixme,
+    Todo,
+}
+
+impl KindFilter {
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "note" | "notes" => Some(Self::Note),
+            "def" | "define" | "definition" | "term" => Some(Self::Define),
+            "fixme" | "errata" => Some(Self::Fixme),
+            "todo" | "pending" => Some(Self::Todo),
+            _ => None,
+        }
+    }
+
+    pub fn matches(self, kind: &Kind) -> bool {
+        matches!(
+            (self, kind),
+            (Self::Note, Kind::Note)
+                | (Self::Define, Kind::Define { .. })
+                | (Self::Fixme, Kind::Fixme)
+                | (Self::Todo, Kind::Todo)
+        )
+    }
+}
+
+impl Query {
+    /// Parse `input`. Whitespace separates clauses, `OR` separates groups,
+    /// `"..."` quotes a phrase, `-` excludes, and `term:`, `tag:`, `kind:` or
+    /// `path:` scope a clause to a field. Unknown prefixes are plain words.
+    pub fn parse(input: &str) -> crate::Result<Self> {
+        let mut groups = Vec::new();
+        let mut group = Vec::new();
+
+        for token in lex(input)? {
+            match token {
+                Token::Or => {
+                    if !group.is_empty() {
+                        groups.push(std::mem::take(&mut group));
+                    }
+                }
+                Token::Clause(clause) => group.push(clause),
+            }
+        }
+        if !group.is_empty() {
+            groups.push(group);
+        }
+
+        if groups.is_empty() {
+            return Err(Error::Query("empty query".into()));
+        }
+        Ok(Self { groups })
+    }
+
+    /// Positive words and phrases, joined for relevance ranking.
+    pub fn ranking_text(&self) -> String {
+        self.groups
+            .iter()
+            .flatten()
+            .filter(|c| !c.negated)
+            .filter_map(|c| match &c.term {
+                Term::Word(w) | Term::Phrase(w) => Some(w.as_str()),
+                Term::Field(_) => None,
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+enum Token {
+    Or,
+    Clause(Clause),
+}
+
+fn lex(input: &str) -> crate::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut negated = false;
+        if c == '-' {
+            chars.next();
+            match chars.peek() {
+                Some(next) if !next.is_whitespace() => negated = true,
+                _ => continue,
+            }
+        }
+
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            let phrase = read_quoted(&mut chars)?;
+            if !phrase.is_empty() {
+                tokens.push(Token::Clause(Clause {
+                    negated,
+                    term: Term::Phrase(phrase),
+                }));
+            }
+            continue;
+        }
+
+        let mut word = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                break;
+            }
+            chars.next();
+            if c == ':' && chars.peek() == Some(&'"') && field_name(&word).is_some() {
+                chars.next();
+                word.push(':');
+                word.push_str(&read_quoted(&mut chars)?);
+                break;
+            }
+            word.push(c);
+        }
+
+        if word == "OR" && !negated {
+            tokens.push(Token::Or);
+            continue;
+        }
+
+        let term = match word.split_once(':') {
+            Some((name, value)) if !value.is_empty() => match field_name(name) {
+                Some(name) => Term::Field(field(name, value)?),
+                None => Term::Word(word),
+            },
+            _ => Term::Word(word),
+        };
+        tokens.push(Token::Clause(Clause { negated, term }));
+    }
+
+    Ok(tokens)
+}
+
+fn read_quoted(chars: &mut std::iter::Peekable<std::str::Chars>) -> crate::Result<String> {
+    let mut text = String::new();
+    for c in chars.by_ref() {
+        if c == '"' {
+            return Ok(text.split_whitespace().collect::<Vec<_>>().join(" "));
+        }
+        text.push(c);
+    }
+    Err(Error::Query("unterminated quote".into()))
+}
+
+fn field_name(name: &str) -> Option<&'static str> {
+    ["term", "tag", "kind", "path"]
+        .into_iter()
+        .find(|f| f.eq_ignore_ascii_case(name))
+}
+
+fn field(name: &str, value: &str) -> crate::Result<Field> {
+    Ok(match name {
+        "term" => Field::Term(value.to_string()),
+        "tag" => Field::Tag(value.to_string()),
+        "path" => Field::Path(value.to_string()),
+        _ => Field::Kind(
+            KindFilter::parse(value)
+                .ok_or_else(|| Error::Query(format!("unknown kind {value:?}")))?,
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(w: &str) -> Clause {
+        Clause {
+            negated: false,
+            term: Term::Word(w.into()),
+        }
+    }
+
+    #[test]
+    fn bare_words_form_one_group() {
+        let q = Query::parse("tax harvested").unwrap();
+        assert_eq!(q.groups, vec![vec![word("tax"), word("harvested")]]);
+    }
+
+    #[test]
+    fn or_splits_groups() {
+        let q = Query::parse("tax OR tithe levy").unwrap();
+        assert_eq!(
+            q.groups,
+            vec![vec![word("tax")], vec![word("tithe"), word("levy")]]
+        );
+    }
+
+    #[test]
+    fn lowercase_or_is_a_word() {
+        let q = Query::parse("this or that").unwrap();
+        assert_eq!(q.groups.len(), 1);
+        assert_eq!(q.groups[0].len(), 3);
+    }
+
+    #[test]
+    fn quoted_phrase_and_exclusion() {
+        let q = Query::parse(r#""the  knives" -cavalry"#).unwrap();
+        assert_eq!(
+            q.groups[0],
+            vec![
+                Clause {
+                    negated: false,
+                    term: Term::Phrase("the knives".into()),
+                },
+                Clause {
+                    negated: true,
+                    term: Term::Word("cavalry".into()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn field_prefixes() {
+        let q = Query::parse(r#"tag:plot Kind:FIXME path:ch04 term:"blue bear""#).unwrap();
+        let terms: Vec<_> = q.groups[0].iter().map(|c| c.term.clone()).collect();
+        assert_eq!(
+            terms,
+            vec![
+                Term::Field(Field::Tag("plot".into())),
+                Term::Field(Field::Kind(KindFilter::Fixme)),
+                Term::Field(Field::Path("ch04".into())),
+                Term::Field(Field::Term("blue bear".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_prefix_is_a_word() {
+        let q = Query::parse("ratio:2").unwrap();
+        assert_eq!(q.groups[0], vec![word("ratio:2")]);
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert!(Query::parse("").is_err());
+        assert!(Query::parse("OR").is_err());
+        assert!(Query::parse(r#""open"#).is_err());
+        assert!(Query::parse("kind:chapter").is_err());
+    }
+
+    #[test]
+    fn ranking_text_skips_fields_and_exclusions() {
+        let q = Query::parse(r#"tax "crop share" -levy tag:plot"#).unwrap();
+        assert_eq!(q.ranking_text(), "tax crop share");
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::configuration;
+
+const FILENAME: &str = "tui.json";
+/// Queries kept per prompt.
+const HISTORY_LEN: usize = 50;
+
+/// TUI layout and prompt history remembered between sessions.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Session {
+    /// Left pane, percent of the terminal width.
+    #[serde(default = "default_left_width")]
+    pub left_width: u16,
+    /// Source pane of the context split, percent of its row.
+    #[serde(default = "default_source_width")]
+    pub source_width: u16,
+    /// Tags listed by note count rather than by name.
+    #[serde(default)]
+    pub tags_by_count: bool,
+    /// Full-text queries, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fts_history: Vec<String>,
+    /// Left-list filters, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub filter_history: Vec<String>,
+}
+
+fn default_left_width() -> u16 {
+    28
+}
+
+fn default_source_width() -> u16 {
+    60
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            left_width: default_left_width(),
+            source_width: default_source_width(),
+            tags_by_count: false,
+            fts_history: Vec::new(),
+            filter_history: Vec::new(),
+        }
+    }
+}
+
+/// Append `query` to `history` (moving a repeat to the end), keeping the
+/// newest `HISTORY_LEN`.
+pub fn remember(history: &mut Vec<String>, query: &str) {
+    let query = query.trim();
+    if query.is_empty() {
+        return;
+    }
+    history.retain(|q| q != query);
+    history.push(query.to_owned());
+    let excess = history.len().saturating_sub(HISTORY_LEN);
+    history.drain(..excess);
+}
+
+/// `tui.json` in the config dir: canonical project dir → session.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Sessions {
+    #[serde(default)]
+    directories: HashMap<String, Session>,
+}
+
+impl Sessions {
+    fn load() -> crate::Result<Self> {
+        match configuration::provider(FILENAME)?.load::<Sessions>() {
+            Ok(sessions) => Ok(sessions),
+            Err(abseil::Error::NotFound) => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// This project's session, or the defaults.
+pub fn load() -> crate::Result<Session> {
+    let mut sessions = Sessions::load()?;
+    Ok(sessions
+        .directories
+        .remove(&configuration::project_key()?)
+        .unwrap_or_default())
+}
+
+pub fn save(session: &Session) -> crate::Result<()> {
+    let mut sessions = Sessions::load()?;
+    sessions
+        .directories
+        .insert(configuration::project_key()?, session.clone());
+    configuration::provider(FILENAME)?.store(&sessions)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remember_moves_repeats_to_the_end() {
+        let mut history = Vec::new();
+        for query in ["army", "  ", "navy", "army "] {
+            remember(&mut history, query);
+        }
+        assert_eq!(history, ["navy", "army"]);
+        for i in 0..HISTORY_LEN {
+            remember(&mut history, &i.to_string());
+        }
+        assert_eq!(history.len(), HISTORY_LEN);
+        assert_eq!(history[0], "0");
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::note::Kind;
+use crate::store::{normalize_tag, normalize_term, NoteStore};
+
+/// Corpus summary behind `notes stats` and the TUI stats panel.
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub kinds: KindCounts,
+    /// Prose words across scanned files, comments excluded.
+    pub words: usize,
+    /// Notes per 1,000 words of prose.
+    pub density: f64,
+    pub terms: usize,
+    pub duplicate_definitions: Vec<TermCount>,
+    pub top_tags: Vec<TagCount>,
+    pub files: Vec<FileStats>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct KindCounts {
+    pub total: usize,
+    pub notes: usize,
+    pub definitions: usize,
+    pub fixmes: usize,
+    pub todos: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TermCount {
+    pub term: String,
+    pub definitions: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub notes: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileStats {
+    pub path: String,
+    pub words: usize,
+    pub notes: usize,
+    pub density: f64,
+    pub fixmes: usize,
+    pub todos: usize,
+}
+
+/// A titled block of label/value rows, shared by the CLI and TUI renderers.
+pub struct Section {
+    pub title: &'static str,
+    pub rows: Vec<(String, String)>,
+}
+
+impl Stats {
+    /// Summarize `store`, keeping the `top` most used tags.
+    pub fn collect(store: &NoteStore, top: usize) -> Self {
+        let mut kinds = KindCounts::default();
+        let mut files: Vec<FileStats> = store
+            .files()
+            .iter()
+            .map(|(path, words)| FileStats::new(path, *words))
+            .collect();
+        let mut by_path: HashMap<&Path, usize> = store
+            .files()
+            .iter()
+            .enumerate()
+            .map(|(i, (path, _))| (path.as_path(), i))
+            .collect();
+        let mut tags: HashMap<String, TagCount> = HashMap::new();
+        let mut terms: HashMap<String, TermCount> = HashMap::new();
+
+        for note in store.notes() {
+            let i = *by_path.entry(&note.path).or_insert_with(|| {
+                files.push(FileStats::new(&note.path, 0));
+                files.len() - 1
+            });
+            let file = &mut files[i];
+            file.notes += 1;
+            kinds.total += 1;
+            match &note.kind {
+                Kind::Note => kinds.notes += 1,
+                Kind::Fixme => {
+                    kinds.fixmes += 1;
+                    file.fixmes += 1;
+                }
+                Kind::Todo => {
+                    kinds.todos += 1;
+                    file.todos += 1;
+                }
+                Kind::Define { term } => {
+                    kinds.definitions += 1;
+                    terms
+                        .entry(normalize_term(term))
+                        .or_insert_with(|| TermCount {
+                            term: term.clone(),
+                            definitions: 0,
+                        })
+                        .definitions += 1;
+                }
+            }
+            // Once per note, however many spellings of a tag it carries.
+            let mut seen = HashSet::new();
+            for tag in &note.tags {
+                let key = normalize_tag(tag);
+                if !seen.insert(key.clone()) {
+                    continue;
+                }
+                tags.entry(key)
+                    .or_insert_with(|| TagCount {
+                        tag: tag.clone(),
+                        notes: 0,
+                    })
+                    .notes += 1;
+            }
+        }
+
+        for file in &mut files {
+            file.density = density(file.notes, file.words);
+        }
+        let words = files.iter().map(|f| f.words).sum();
+
+        let mut top_tags: Vec<TagCount> = tags.into_values().collect();
+        top_tags.sort_by(|a, b| b.notes.cmp(&a.notes).then_with(|| a.tag.cmp(&b.tag)));
+        top_tags.truncate(top);
+
+        let term_count = terms.len();
+        let mut duplicate_definitions: Vec<TermCount> =
+            terms.into_values().filter(|t| t.definitions > 1).collect();
+        duplicate_definitions.sort_by_cached_key(|t| t.term.to_lowercase());
+
+        Self {
+            density: density(kinds.total, words),
+            kinds,
+            words,
+            terms: term_count,
+            duplicate_definitions,
+            top_tags,
+            files,
+        }
+    }
+
+    pub fn sections(&self) -> Vec<Section> {
+        let k = &self.kinds;
+        let mut glossary = vec![
+            ("defined terms".into(), self.terms.to_string()),
+            (
+                "duplicate definitions".into(),
+                self.duplicate_definitions.len().to_string(),
+            ),
+        ];
+        glossary.extend(
+            self.duplicate_definitions
+                .iter()
+                .map(|t| (format!("  {}", t.term), format!("{}×", t.definitions))),
+        );
+
+        vec![
+            Section {
+                title: "Notes",
+                rows: vec![
+                    ("total".into(), k.total.to_string()),
+                    ("notes".into(), k.notes.to_string()),
+                    ("definitions".into(), k.definitions.to_string()),
+                    ("FIXME".into(), k.fixmes.to_string()),
+                    ("TODO".into(), k.todos.to_string()),
+                    ("words of prose".into(), self.words.to_string()),
+                    ("per 1,000 words".into(), format!("{:.1}", self.density)),
+                ],
+            },
+            Section {
+                title: "Glossary",
+                rows: glossary,
+            },
+            Section {
+                title: "Top tags",
+                rows: self
+                    .top_tags
+                    .iter()
+                    .map(|t| (format!("#{}", t.tag), t.notes.to_string()))
+                    .collect(),
+            },
+            Section {
+                title: "Files",
+                rows: self
+                    .files
+                    .iter()
+                    .map(|f| {
+                        let value = format!(
+                            "{} notes, {:.1}/1k words, {} FIXME, {} TODO",
+                            f.notes, f.density, f.fixmes, f.todos
+                        );
+                        (f.path.clone(), value)
+                    })
+                    .collect(),
+            },
+        ]
+    }
+}
+
+impl FileStats {
+    fn new(path: &Path, words: usize) -> Self {
+        Self {
+            path: path.display().to_string(),
+            words,
+            notes: 0,
+            density: 0.0,
+            fixmes: 0,
+            todos: 0,
+        }
+    }
+}
+
+fn density(notes: usize, words: usize) -> f64 {
+    if words == 0 {
+        0.0
+    } else {
+        notes as f64 * 1000.0 / words as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::Parser;
+
+    #[test]
+    fn counts_kinds_tags_and_duplicates() {
+        let parser = Parser::new();
+        let mut notes = parser.parse_file(
+            Path::new("ch01.md"),
+            "<!-- NOTE def tithe a tenth. -->\n\
+             <!-- FIXME dates #plot -->\n\
+             <!-- TODO more #plot #army -->\n",
+        );
+        notes.extend(parser.parse_file(
+            Path::new("ch02.md"),
+            "<!-- NOTE def Tithe a tax. -->\n<!-- NOTE march #army #plot -->\n",
+        ));
+        let stats = Stats::collect(&NoteStore::from_notes(notes), 1);
+
+        assert_eq!(stats.kinds.total, 5);
+        assert_eq!(stats.kinds.definitions, 2);
+        assert_eq!(stats.terms, 1);
+        assert_eq!(stats.duplicate_definitions[0].definitions, 2);
+        assert_eq!(stats.top_tags.len(), 1);
+        assert_eq!(stats.top_tags[0].tag, "plot");
+        assert_eq!(stats.top_tags[0].notes, 3);
+        assert_eq!(stats.files[0].fixmes, 1);
+        assert_eq!(stats.files[0].todos, 1);
+        assert_eq!(stats.files[1].notes, 2);
+    }
+
+    #[test]
+    fn mixed_case_tags_count_once_per_note() {
+        let notes = Parser::new().parse_file(
+            Path::new("ch01.md"),
+            "<!-- NOTE twist #Plot #plot -->\n<!-- NOTE reveal #PLOT -->\n\
+             <!-- NOTE def Tithe a tenth. -->\n<!-- NOTE def tithe  a tax. -->\n",
+        );
+        let store = NoteStore::from_notes(notes);
+        let stats = Stats::collect(&store, 5);
+        assert_eq!(stats.top_tags.len(), 1);
+        assert_eq!(stats.top_tags[0].tag, "Plot");
+        assert_eq!(stats.top_tags[0].notes, 2);
+        assert_eq!(stats.top_tags[0].notes, store.search_tag("plot").len());
+        assert_eq!(stats.terms, 1);
+        assert_eq!(stats.duplicate_definitions[0].definitions, 2);
+    }
+
+    #[test]
+    fn density_is_per_thousand_words() {
+        assert_eq!(density(3, 1500), 2.0);
+        assert_eq!(density(3, 0), 0.0);
+    }
+}
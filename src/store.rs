@@ -1,7 +1,7 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     fs,
-    path::Path,
+    path::{Component, Path, PathBuf},
 };
 
 use glob::glob;
@@ -15,10 +15,19 @@ pub struct NoteStore {
     by_term: HashMap<String, Vec<usize>>,
     fixmes: Vec<usize>,
     todos: Vec<usize>,
+    /// Every scanned file with its prose word count, in scan order.
+    files: Vec<(PathBuf, usize)>,
 }
 
 impl NoteStore {
     pub fn load(pattern: &str) -> crate::Result<Self> {
+        Self::load_within(pattern, &[])
+    }
+
+    /// Like [`NoteStore::load`], but only files that are, or sit under, one
+    /// of `scope` (relative to the working directory). An empty scope keeps
+    /// every file the glob matches.
+    pub fn load_within(pattern: &str, scope: &[PathBuf]) -> crate::Result<Self> {
         let parser = Parser::new();
         let mut store = Self::default();
 
@@ -30,7 +39,7 @@ impl NoteStore {
                     continue;
                 }
             };
-            if !path.is_file() {
+            if !path.is_file() || !in_scope(&path, scope) {
                 continue;
             }
             store.index_file(&parser, &path)?;
@@ -44,6 +53,8 @@ impl NoteStore {
         for note in parser.parse_file(path, &source) {
             self.push(note);
         }
+        self.files
+            .push((path.to_path_buf(), parser.prose_words(&source)));
         Ok(())
     }
 
@@ -72,6 +83,11 @@ impl NoteStore {
         self.notes.push(note);
     }
 
+    /// Scanned files and their prose word counts (comments excluded).
+    pub fn files(&self) -> &[(PathBuf, usize)] {
+        &self.files
+    }
+
     pub fn notes(&self) -> &[Note] {
         &self.notes
     }
@@ -95,6 +111,23 @@ impl NoteStore {
         tags
     }
 
+    /// How many of `notes` carry each tag, keyed by the names [`tags`]
+    /// returns; tags none of them carry are left out.
+    ///
+    /// [`tags`]: NoteStore::tags
+    pub fn tag_counts(&self, notes: &[&Note]) -> HashMap<String, usize> {
+        let mut by_key: HashMap<String, usize> = HashMap::new();
+        for note in notes {
+            for key in tag_keys(note) {
+                *by_key.entry(key).or_default() += 1;
+            }
+        }
+        self.tags()
+            .into_iter()
+            .filter_map(|tag| Some((tag.to_owned(), *by_key.get(&normalize_tag(tag))?)))
+            .collect()
+    }
+
     pub fn terms(&self) -> Vec<&str> {
         let mut terms: Vec<_> = self
             .notes
@@ -130,6 +163,30 @@ impl NoteStore {
         ids.into_iter().map(|id| &self.notes[id]).collect()
     }
 
+    /// Notes carrying ALL the given tags, or with `any` at least one, minus
+    /// notes carrying any of `exclude`. No `tags` → every note but those.
+    pub fn select_tags(&self, tags: &[String], any: bool, exclude: &[String]) -> Vec<&Note> {
+        let notes = if any && !tags.is_empty() {
+            let ids: BTreeSet<usize> = tags
+                .iter()
+                .filter_map(|t| self.by_tag.get(&normalize_tag(t)))
+                .flatten()
+                .copied()
+                .collect();
+            ids.into_iter().map(|id| &self.notes[id]).collect()
+        } else {
+            self.search_tags(tags)
+        };
+        if exclude.is_empty() {
+            return notes;
+        }
+        let excluded: HashSet<String> = exclude.iter().map(|t| normalize_tag(t)).collect();
+        notes
+            .into_iter()
+            .filter(|n| !n.tags.iter().any(|t| excluded.contains(&normalize_tag(t))))
+            .collect()
+    }
+
     pub fn search_tag(&self, tag: &str) -> Vec<&Note> {
         let key = normalize_tag(tag);
         self.by_tag
@@ -185,9 +242,111 @@ impl NoteStore {
     pub fn get(&self, id: usize) -> Option<&Note> {
         self.notes.get(id)
     }
+
+    /// Swap in an edited note, keeping the tag, term, and kind indexes
+    /// (sorted by id) in step.
+    pub fn replace(&mut self, id: usize, note: Note) {
+        let old = std::mem::replace(&mut self.notes[id], note);
+        self.unindex(id, &old);
+        let note = &self.notes[id];
+        for key in tag_keys(note) {
+            insert_sorted(self.by_tag.entry(key).or_default(), id);
+        }
+        if let Kind::Define { term } = &note.kind {
+            insert_sorted(self.by_term.entry(normalize_term(term)).or_default(), id);
+        }
+        if note.is_fixme() {
+            insert_sorted(&mut self.fixmes, id);
+        }
+        if note.is_todo() {
+            insert_sorted(&mut self.todos, id);
+        }
+    }
+
+    fn unindex(&mut self, id: usize, note: &Note) {
+        for key in tag_keys(note) {
+            if let Some(ids) = self.by_tag.get_mut(&key) {
+                ids.retain(|&i| i != id);
+                if ids.is_empty() {
+                    self.by_tag.remove(&key);
+                }
+            }
+        }
+        if let Kind::Define { term } = &note.kind {
+            let key = normalize_term(term);
+            if let Some(ids) = self.by_term.get_mut(&key) {
+                ids.retain(|&i| i != id);
+                if ids.is_empty() {
+                    self.by_term.remove(&key);
+                }
+            }
+        }
+        self.fixmes.retain(|&i| i != id);
+        self.todos.retain(|&i| i != id);
+    }
+
+    /// Move notes below `line` in `path` by `delta` lines, after an edit
+    /// there added or removed line breaks.
+    pub fn shift_lines(&mut self, path: &Path, line: usize, delta: isize) {
+        if delta == 0 {
+            return;
+        }
+        for note in &mut self.notes {
+            if note.path == path && note.line > line {
+                note.line = note.line.saturating_add_signed(delta);
+            }
+        }
+    }
+
+    pub fn tag_ids(&self, tag: &str) -> &[usize] {
+        self.by_tag
+            .get(&normalize_tag(tag))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn term_ids(&self, term: &str) -> &[usize] {
+        self.by_term
+            .get(&normalize_term(term))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn define_ids(&self) -> impl Iterator<Item = usize> + '_ {
+        self.by_term.values().flatten().copied()
+    }
+
+    pub fn fixme_ids(&self) -> &[usize] {
+        &self.fixmes
+    }
+
+    pub fn todo_ids(&self) -> &[usize] {
+        &self.todos
+    }
+}
+
+#[cfg(test)]
+impl NoteStore {
+    pub(crate) fn from_notes(notes: Vec<Note>) -> Self {
+        let mut store = Self::default();
+        for note in notes {
+            store.push(note);
+        }
+        store
+    }
+
+    pub(crate) fn add_file_for_test(&mut self, path: &str) {
+        self.files.push((PathBuf::from(path), 0));
+    }
 }
 
-fn normalize_tag(tag: &str) -> String {
+fn insert_sorted(ids: &mut Vec<usize>, id: usize) {
+    if let Err(at) = ids.binary_search(&id) {
+        ids.insert(at, id);
+    }
+}
+
+pub fn normalize_tag(tag: &str) -> String {
     tag.trim()
         .trim_start_matches('#')
         .replace(' ', "_")
@@ -197,9 +356,12 @@ fn normalize_tag(tag: &str) -> String {
 /// Normalized tag keys for a note: the explicit `#tags` plus the defined term
 /// (if any), deduped so the note is indexed once per key.
 fn tag_keys(note: &Note) -> Vec<String> {
-    let mut keys: Vec<String> = note.tags.iter().map(|t| normalize_tag(t)).collect();
-    if let Kind::Define { term } = &note.kind {
-        let k = normalize_tag(term);
+    let mut keys: Vec<String> = Vec::new();
+    let term = match &note.kind {
+        Kind::Define { term } => Some(term),
+        _ => None,
+    };
+    for k in note.tags.iter().chain(term).map(|t| normalize_tag(t)) {
         if !keys.contains(&k) {
             keys.push(k);
         }
@@ -207,14 +369,38 @@ fn tag_keys(note: &Note) -> Vec<String> {
     keys
 }
 
-fn normalize_term(term: &str) -> String {
+fn in_scope(path: &Path, scope: &[PathBuf]) -> bool {
+    let path = normalize_path(path);
+    scope.is_empty() || scope.iter().any(|s| path.starts_with(normalize_path(s)))
+}
+
+/// Drop `.` components and resolve `..` lexically, so `./ch01.md` and
+/// `sub/../ch01.md` compare equal to `ch01.md`. A leading `..` is kept.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(out.components().next_back(), Some(Component::Normal(_))) =>
+            {
+                out.pop();
+            }
+            Component::ParentDir if out.has_root() => {}
+            component => out.push(component),
+        }
+    }
+    out
+}
+
+pub fn normalize_term(term: &str) -> String {
     term.trim().to_ascii_lowercase()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::note::{Kind, Note};
+    use crate::note::{Kind, Note, Status};
     use std::path::PathBuf;
 
     fn define_note(term: &str, gloss: &str) -> Note {
@@ -222,6 +408,7 @@ mod tests {
             path: PathBuf::from("t.md"),
             line: 1,
             kind: Kind::Define { term: term.into() },
+            status: Status::Open,
             tags: vec![],
             text: gloss.into(),
         }
@@ -232,6 +419,7 @@ mod tests {
             path: PathBuf::from("t.md"),
             line: 1,
             kind: Kind::Note,
+            status: Status::Open,
             tags: tags.iter().map(|t| (*t).to_string()).collect(),
             text: text.into(),
         }
@@ -245,6 +433,54 @@ mod tests {
         store
     }
 
+    #[test]
+    fn scope_matches_files_and_directories() {
+        let scope = [PathBuf::from("./chapters/"), PathBuf::from("intro.md")];
+        assert!(in_scope(Path::new("chapters/ch04.md"), &scope));
+        assert!(in_scope(Path::new("./intro.md"), &scope));
+        assert!(in_scope(Path::new("chapters/../intro.md"), &scope));
+        assert!(!in_scope(Path::new("chapters/../ch04.md"), &scope));
+        assert!(!in_scope(Path::new("chapters-old/ch04.md"), &scope));
+        assert!(!in_scope(Path::new("intro.md.bak"), &scope));
+        assert!(in_scope(Path::new("anything.md"), &[]));
+        assert!(in_scope(
+            Path::new("ch01.md"),
+            &[PathBuf::from("sub/../ch01.md")]
+        ));
+        assert_eq!(
+            normalize_path(Path::new("../a/./b/../c")),
+            Path::new("../a/c")
+        );
+        assert_eq!(normalize_path(Path::new("/../a")), Path::new("/a"));
+    }
+
+    #[test]
+    fn replace_keeps_indexes_in_step() {
+        let mut store = store_with(vec![
+            tagged_note("a", &["army"]),
+            tagged_note("b", &["army", "plot"]),
+            tagged_note("c", &["plot"]),
+        ]);
+        let edited = Note {
+            tags: vec!["plot".into()],
+            ..store.get(0).unwrap().clone()
+        };
+        store.replace(0, edited);
+        assert_eq!(store.tag_ids("army"), [1]);
+        assert_eq!(store.tag_ids("plot"), [0, 1, 2]);
+        assert_eq!(store.search_tags(&["plot".into()]).len(), 3);
+    }
+
+    #[test]
+    fn shift_lines_moves_later_notes_in_the_file() {
+        let mut notes = vec![tagged_note("a", &[]), tagged_note("b", &[])];
+        notes[1].line = 4;
+        let mut store = store_with(notes);
+        store.shift_lines(Path::new("t.md"), 1, -2);
+        assert_eq!(store.get(0).unwrap().line, 1);
+        assert_eq!(store.get(1).unwrap().line, 2);
+    }
+
     #[test]
     fn define_lookup_single_word() {
         let store = store_with(vec![define_note("spearsheaves", "a tax.")]);
@@ -331,6 +567,7 @@ mod tests {
             path: PathBuf::from("t.md"),
             line: 1,
             kind: Kind::Define { term: "foo".into() },
+            status: Status::Open,
             tags: vec!["foo".into()],
             text: "the gloss".into(),
         };
@@ -385,6 +622,42 @@ mod tests {
         assert_eq!(found.len(), 1);
     }
 
+    #[test]
+    fn select_tags_unites_and_excludes() {
+        let store = store_with(vec![
+            tagged_note("one", &["a", "b"]),
+            tagged_note("two", &["a", "draft"]),
+            tagged_note("three", &["b"]),
+            tagged_note("four", &["c"]),
+        ]);
+        let texts = |tags: &[&str], any, exclude: &[&str]| -> Vec<String> {
+            let tags: Vec<String> = tags.iter().map(|t| t.to_string()).collect();
+            let exclude: Vec<String> = exclude.iter().map(|t| t.to_string()).collect();
+            store
+                .select_tags(&tags, any, &exclude)
+                .iter()
+                .map(|n| n.text.clone())
+                .collect()
+        };
+        assert_eq!(texts(&["a", "b"], false, &[]), ["one"]);
+        assert_eq!(texts(&["b", "a"], true, &[]), ["one", "two", "three"]);
+        assert_eq!(texts(&["a", "b"], true, &["#Draft"]), ["one", "three"]);
+        assert_eq!(texts(&[], false, &["a"]), ["three", "four"]);
+    }
+
+    #[test]
+    fn tag_counts_use_listed_names() {
+        let store = store_with(vec![
+            tagged_note("one", &["Army", "navy"]),
+            tagged_note("two", &["army"]),
+        ]);
+        let notes: Vec<&Note> = store.notes().iter().collect();
+        let counts = store.tag_counts(&notes);
+        assert_eq!(counts["Army"], 2);
+        assert_eq!(counts["navy"], 1);
+        assert_eq!(store.tag_counts(&notes[1..]).get("navy"), None);
+    }
+
     #[test]
     fn search_tag_spaces_and_underscores_interchangeable() {
         let store = store_with(vec![
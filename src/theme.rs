@@ -0,0 +1,313 @@
+use std::{path::PathBuf, str::FromStr};
+
+use owo_colors::{AnsiColors, DynColors, XtermColors};
+use ratatui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Serialize};
+
+use crate::configuration;
+use crate::error::Error;
+
+const FILENAME: &str = "theme.json";
+
+/// Colors and text effects shared by CLI output and the TUI, loaded from
+/// `theme.json` in the config dir. Missing entries keep their defaults.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Theme {
+    pub fixme: StyleSpec,
+    pub todo: StyleSpec,
+    pub term: StyleSpec,
+    pub location: StyleSpec,
+    pub tag: StyleSpec,
+    pub selection: StyleSpec,
+    pub focus: StyleSpec,
+    pub heading: StyleSpec,
+    pub added: StyleSpec,
+    pub removed: StyleSpec,
+    /// FIXMEs and TODOs marked done, in the TUI notes list.
+    pub done: StyleSpec,
+    /// TUI tags that would leave the notes pane empty.
+    pub muted: StyleSpec,
+    /// The `×n` count after glossary terms defined more than once.
+    pub badge: StyleSpec,
+}
+
+/// One themed style. `fg` is a color name (`red`, `light-blue`, …), an xterm
+/// index (`208`), or `#rrggbb`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StyleSpec {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fg: Option<String>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub bold: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub italic: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub underline: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub dim: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub reversed: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub strikethrough: bool,
+}
+
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        let bold = StyleSpec {
+            bold: true,
+            ..Default::default()
+        };
+        Self {
+            fixme: StyleSpec {
+                fg: Some("red".into()),
+                ..bold.clone()
+            },
+            todo: StyleSpec {
+                fg: Some("yellow".into()),
+                ..bold.clone()
+            },
+            term: bold.clone(),
+            location: StyleSpec {
+                dim: true,
+                ..Default::default()
+            },
+            tag: StyleSpec {
+                underline: true,
+                ..Default::default()
+            },
+            selection: StyleSpec {
+                reversed: true,
+                ..Default::default()
+            },
+            focus: bold.clone(),
+            heading: bold,
+            added: StyleSpec {
+                fg: Some("green".into()),
+                ..Default::default()
+            },
+            removed: StyleSpec {
+                fg: Some("red".into()),
+                ..Default::default()
+            },
+            done: StyleSpec {
+                strikethrough: true,
+                ..Default::default()
+            },
+            muted: StyleSpec {
+                dim: true,
+                ..Default::default()
+            },
+            badge: StyleSpec {
+                fg: Some("red".into()),
+                bold: true,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl Theme {
+    /// Load `theme.json`, or the default theme if there is none.
+    pub fn load() -> crate::Result<Self> {
+        let theme = match configuration::provider(FILENAME)?.load::<Theme>() {
+            Ok(theme) => theme,
+            Err(abseil::Error::NotFound) => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+        theme.validate()?;
+        Ok(theme)
+    }
+
+    /// Where `theme.json` is read from (it need not exist).
+    pub fn path() -> crate::Result<PathBuf> {
+        let provider = configuration::provider(FILENAME)?;
+        Ok(provider.location().path().join(FILENAME))
+    }
+
+    fn specs(&self) -> [(&'static str, &StyleSpec); 13] {
+        [
+            ("fixme", &self.fixme),
+            ("todo", &self.todo),
+            ("term", &self.term),
+            ("location", &self.location),
+            ("tag", &self.tag),
+            ("selection", &self.selection),
+            ("focus", &self.focus),
+            ("heading", &self.heading),
+            ("added", &self.added),
+            ("removed", &self.removed),
+            ("done", &self.done),
+            ("muted", &self.muted),
+            ("badge", &self.badge),
+        ]
+    }
+
+    fn validate(&self) -> crate::Result<()> {
+        for (name, spec) in self.specs() {
+            if let Some(fg) = &spec.fg {
+                Color::from_str(fg)
+                    .map_err(|_| Error::Config(format!("theme: {name}: unknown color {fg:?}")))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Same effects, no colors (for `NO_COLOR` in the TUI).
+    pub fn without_colors(mut self) -> Self {
+        for spec in [
+            &mut self.fixme,
+            &mut self.todo,
+            &mut self.term,
+            &mut self.location,
+            &mut self.tag,
+            &mut self.selection,
+            &mut self.focus,
+            &mut self.heading,
+            &mut self.added,
+            &mut self.removed,
+            &mut self.done,
+            &mut self.muted,
+            &mut self.badge,
+        ] {
+            spec.fg = None;
+        }
+        self
+    }
+}
+
+impl StyleSpec {
+    fn color(&self) -> Option<Color> {
+        self.fg.as_deref().and_then(|fg| Color::from_str(fg).ok())
+    }
+
+    pub fn tui(&self) -> Style {
+        let mut style = Style::default();
+        if let Some(color) = self.color() {
+            style = style.fg(color);
+        }
+        for (on, modifier) in [
+            (self.bold, Modifier::BOLD),
+            (self.italic, Modifier::ITALIC),
+            (self.underline, Modifier::UNDERLINED),
+            (self.dim, Modifier::DIM),
+            (self.reversed, Modifier::REVERSED),
+            (self.strikethrough, Modifier::CROSSED_OUT),
+        ] {
+            if on {
+                style = style.add_modifier(modifier);
+            }
+        }
+        style
+    }
+
+    pub fn cli(&self) -> owo_colors::Style {
+        let mut style = owo_colors::Style::new();
+        if let Some(color) = self.color().and_then(ansi) {
+            style = style.color(color);
+        }
+        if self.bold {
+            style = style.bold();
+        }
+        if self.italic {
+            style = style.italic();
+        }
+        if self.underline {
+            style = style.underline();
+        }
+        if self.dim {
+            style = style.dimmed();
+        }
+        if self.reversed {
+            style = style.reversed();
+        }
+        if self.strikethrough {
+            style = style.strikethrough();
+        }
+        style
+    }
+}
+
+fn ansi(color: Color) -> Option<DynColors> {
+    let ansi = match color {
+        Color::Reset => return None,
+        Color::Black => AnsiColors::Black,
+        Color::Red => AnsiColors::Red,
+        Color::Green => AnsiColors::Green,
+        Color::Yellow => AnsiColors::Yellow,
+        Color::Blue => AnsiColors::Blue,
+        Color::Magenta => AnsiColors::Magenta,
+        Color::Cyan => AnsiColors::Cyan,
+        Color::Gray => AnsiColors::White,
+        Color::DarkGray => AnsiColors::BrightBlack,
+        Color::LightRed => AnsiColors::BrightRed,
+        Color::LightGreen => AnsiColors::BrightGreen,
+        Color::LightYellow => AnsiColors::BrightYellow,
+        Color::LightBlue => AnsiColors::BrightBlue,
+        Color::LightMagenta => AnsiColors::BrightMagenta,
+        Color::LightCyan => AnsiColors::BrightCyan,
+        Color::White => AnsiColors::BrightWhite,
+        Color::Indexed(i) => return Some(DynColors::Xterm(XtermColors::from(i))),
+        Color::Rgb(r, g, b) => return Some(DynColors::Rgb(r, g, b)),
+    };
+    Some(DynColors::Ansi(ansi))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_theme_keeps_defaults() {
+        let theme: Theme = serde_json::from_str(r##"{ "fixme": { "fg": "#ff8800" } }"##).unwrap();
+        assert_eq!(theme.fixme.fg.as_deref(), Some("#ff8800"));
+        assert!(!theme.fixme.bold);
+        assert_eq!(theme.todo, Theme::default().todo);
+        assert!(theme.validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_colors() {
+        let theme: Theme =
+            serde_json::from_str(r#"{ "tag": { "fg": "chartreuse-ish" } }"#).unwrap();
+        assert!(theme.validate().is_err());
+        let theme: Theme = serde_json::from_str(r#"{ "done": { "fg": "nope" } }"#).unwrap();
+        assert!(theme.validate().is_err());
+        let theme: Theme = serde_json::from_str(r#"{ "badge": { "fg": "nope" } }"#).unwrap();
+        assert!(theme.validate().is_err());
+    }
+
+    #[test]
+    fn styles_map_to_both_backends() {
+        let spec = StyleSpec {
+            fg: Some("light-red".into()),
+            bold: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            spec.tui(),
+            Style::default()
+                .fg(Color::LightRed)
+                .add_modifier(Modifier::BOLD)
+        );
+        use owo_colors::OwoColorize;
+        assert_eq!(format!("{}", "x".style(spec.cli())), "\x1b[91;1mx\x1b[0m");
+    }
+
+    #[test]
+    fn without_colors_keeps_effects() {
+        let theme = Theme::default().without_colors();
+        assert_eq!(theme.fixme.fg, None);
+        assert!(theme.fixme.bold);
+        assert!(theme
+            .done
+            .tui()
+            .add_modifier
+            .contains(Modifier::CROSSED_OUT));
+    }
+}
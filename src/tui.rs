@@ -1,9 +1,15 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ffi::OsString;
 use std::io::{self, stdout};
+use std::path::{Component, Path};
+use std::time::Duration;
 
 use arboard::Clipboard;
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, MouseButton,
+        MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -12,21 +18,80 @@ use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Modifier, Style},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Sparkline, Wrap},
     Frame, Terminal,
 };
 
-use crate::format::{plain_body, styled_words, BodyStyle};
-use crate::note::{Kind, Note};
-use crate::search::FtsIndex;
-use crate::store::NoteStore;
+use crate::context::SourceContext;
+use crate::date;
+use crate::export::{self, ExportFormat};
+use crate::format::{plain_body, BodyStyle, BodyWord, Formatter};
+use crate::git::{Blame, History};
+use crate::keymap::{Action, Key, Keymap, Lookup};
+use crate::listing::{self, GroupBy};
+use crate::note::{Kind, Note, Status};
+use crate::progress::{self, Snapshot};
+use crate::search::{Analyzer, FtsIndex};
+use crate::session::{self, Session};
+use crate::stats::Stats;
+use crate::store::{normalize_path, NoteStore};
+use crate::theme::Theme;
+use crate::view::{self, Catalog, OverrideKind, View};
+use crate::writeback;
+
+/// Tags listed in the stats panel (same default as `notes stats`).
+const STATS_TOP_TAGS: usize = 10;
+/// Narrowest and widest a resized pane may get, in percent.
+const PANE_MIN: u16 = 10;
+const PANE_MAX: u16 = 80;
+/// How long a key that starts a longer binding (`g` of `g g`) waits for
+/// the next one before it runs on its own.
+const SEQUENCE_TIMEOUT: Duration = Duration::from_millis(500);
 
 enum Mode {
     Browse,
     Filter,
     Fts,
-    Detail { scroll: u16 },
-    Help,
+    /// Incremental find in the notes list (`F`).
+    Find,
+    /// `source` shows the file around the note instead of its body.
+    Detail {
+        scroll: u16,
+        source: bool,
+    },
+    Stats {
+        scroll: u16,
+    },
+    /// Every definition of `App.compare` side by side (`D`).
+    Compare {
+        scroll: u16,
+    },
+    Progress,
+    Edit(EditAction),
+    /// `:` fuzzy list of every action.
+    Palette,
+    /// Destination prompt for exporting the marked notes (`Y`).
+    Export(ExportFormat),
+    /// Saved views list (`v`), and the name prompt for saving one (`V`).
+    Views,
+    SaveView,
+    Help {
+        scroll: u16,
+    },
+}
+
+/// Popup input on the selected note; each writes back to its comment.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EditAction {
+    AddTag,
+    RemoveTag,
+    Text,
+}
+
+/// How to take back one write-back: the note and its comment body before.
+struct Undo {
+    id: usize,
+    previous: String,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -35,17 +100,82 @@ enum Focus {
     Notes,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum Catalog {
-    Tags,
-    Glossary,
+/// Scanned files and their folders for the files catalog, as path keys
+/// (folders end in `/`) in tree order, with notes under each key.
+struct FileTree {
+    keys: Vec<String>,
+    notes: HashMap<String, usize>,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum OverrideKind {
-    Fts,
-    Errata,
-    Pending,
+impl FileTree {
+    fn build(store: &NoteStore) -> Self {
+        let mut keys = BTreeSet::new();
+        for (path, _) in store.files() {
+            let parts = path_parts(path);
+            for depth in 1..parts.len() {
+                keys.insert((parts[..depth].to_vec(), true));
+            }
+            keys.insert((parts, false));
+        }
+        let keys: Vec<String> = keys
+            .into_iter()
+            .map(|(parts, dir)| tree_key(&parts, dir))
+            .collect();
+        // Each note counts toward its file and every folder above it.
+        let mut notes: HashMap<String, usize> = HashMap::new();
+        for note in store.notes() {
+            let parts = path_parts(&note.path);
+            for depth in 1..=parts.len() {
+                let key = tree_key(&parts[..depth], depth < parts.len());
+                *notes.entry(key).or_default() += 1;
+            }
+        }
+        Self { keys, notes }
+    }
+}
+
+/// Components of `path` after `normalize_path`, with the root as `/`.
+fn path_parts(path: &Path) -> Vec<OsString> {
+    normalize_path(path)
+        .components()
+        .map(|c| match c {
+            Component::RootDir => OsString::from("/"),
+            c => c.as_os_str().to_owned(),
+        })
+        .collect()
+}
+
+/// Tree key for a path's components: joined with `/` on every platform, so
+/// `tree_label` can split it, with folders ending in `/`.
+fn tree_key(parts: &[OsString], dir: bool) -> String {
+    let mut key = String::new();
+    for (i, part) in parts.iter().enumerate() {
+        let part = part.to_string_lossy();
+        if i > 0 && !key.ends_with('/') {
+            key.push('/');
+        }
+        key.push_str(&part);
+    }
+    if dir && !key.ends_with('/') {
+        key.push('/');
+    }
+    key
+}
+
+/// Folder depth and the name shown for a tree key.
+fn tree_label(key: &str) -> (usize, &str) {
+    let trimmed = key.trim_end_matches('/');
+    match trimmed.rfind('/') {
+        Some(i) => (trimmed.matches('/').count(), &key[i + 1..]),
+        None => (0, key),
+    }
+}
+
+/// A line in the notes pane: a note by store id, or (grouped by file) a
+/// file header.
+enum Row {
+    File { title: String, notes: usize },
+    Note(usize),
 }
 
 struct Override {
@@ -53,14 +183,46 @@ struct Override {
     ids: Vec<usize>,
 }
 
+/// Where the last frame drew each pane, for mouse hit-testing.
+#[derive(Default)]
+struct Panes {
+    left: Rect,
+    notes: Rect,
+    /// Preview row, split with `source` when the context view is on.
+    preview: Rect,
+    source: Option<Rect>,
+}
+
+/// A pane border being dragged with the mouse.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Border {
+    Left,
+    Source,
+}
+
 struct App {
     store: NoteStore,
     fts: FtsIndex,
+    formatter: Formatter,
+    theme: Theme,
     tags: Vec<String>,
     /// Tags toggled on with space; notes pane shows notes carrying all of them.
     selected: BTreeSet<String>,
-    /// Built on first `g` from store.by_term (already in memory).
+    /// `count_tags` for the current selection, kept by `refresh_tag_counts`.
+    tag_counts: HashMap<String, usize>,
+    /// Tags whose notes are left out (`[-]`).
+    excluded: BTreeSet<String>,
+    /// Notes need any picked tag rather than all of them.
+    any_tag: bool,
+    /// Built on first `t` from store.by_term (already in memory).
     terms: Option<Vec<String>>,
+    /// Built on first `d` from the scanned file list.
+    files: Option<FileTree>,
+    /// Notes pane shows file headers (`z`); collapsed ones hide their notes.
+    group_files: bool,
+    collapsed: HashSet<String>,
+    /// Notes pane rows, rebuilt by `refresh_rows` when what they show changes.
+    rows: Vec<Row>,
     left_state: ListState,
     note_state: ListState,
     filter: String,
@@ -70,12 +232,57 @@ struct App {
     catalog: Catalog,
     /// When set, right pane shows these note ids (FTS, errata, or pending)
     override_state: Option<Override>,
+    /// Built on first `s`.
+    stats: Option<Stats>,
+    /// Recorded `notes snapshot` history, read on first `P`.
+    snapshots: Option<Vec<Snapshot>>,
+    /// `git blame`, run per file the first time one of its notes is opened.
+    history: History,
+    /// Blame for the note in the detail overlay; `None` outside git.
+    detail_blame: Option<Blame>,
+    /// Preview pane split with the source file around the note (`c`).
+    show_context: bool,
+    /// Source of the selected note, by store id; reloaded when it changes.
+    context: Option<(usize, SourceContext)>,
+    /// Lines moved from the centred comment with `J` / `K`.
+    context_scroll: isize,
+    keymap: Keymap,
+    /// Term whose definitions `Mode::Compare` shows.
+    compare: String,
+    /// Keys typed so far of a longer binding.
+    pending: Vec<Key>,
+    /// Notes-list find query (`F`, then `n` / `N`), and the row selected
+    /// when the prompt opened, restored by Esc.
+    find: String,
+    find_origin: Option<usize>,
+    /// Pane widths, saved for the next run.
+    session: Session,
+    panes: Panes,
+    drag: Option<Border>,
+    /// Text typed into the edit popup.
+    input: String,
+    /// Store ids of notes marked for a batch yank or export.
+    marked: BTreeSet<usize>,
+    /// Export kept for stdout, printed after the TUI closes.
+    stdout: Option<String>,
+    /// Saved views, read on first `v` / `V`.
+    views: Option<BTreeMap<String, View>>,
+    views_selected: usize,
+    /// Palette query and the highlighted match.
+    palette: String,
+    palette_selected: usize,
+    /// History entry shown in the filter / FTS prompt (Up / Down), and the
+    /// text typed before recalling it.
+    recall: Option<usize>,
+    draft: String,
+    /// Edits written back this session, newest last.
+    undo: Vec<Undo>,
     status: String,
 }
 
 impl App {
-    fn new(store: NoteStore) -> Self {
-        let fts = FtsIndex::build(&store);
+    fn new(store: NoteStore, analyzer: Analyzer, formatter: Formatter, theme: Theme) -> Self {
+        let fts = FtsIndex::build(&store, analyzer);
         let tags: Vec<String> = store.tags().into_iter().map(str::to_owned).collect();
         let mut left_state = ListState::default();
         if !tags.is_empty() {
@@ -84,9 +291,18 @@ impl App {
         let mut app = Self {
             store,
             fts,
+            formatter,
+            theme,
             tags,
             selected: BTreeSet::new(),
+            tag_counts: HashMap::new(),
+            excluded: BTreeSet::new(),
+            any_tag: false,
             terms: None,
+            files: None,
+            group_files: false,
+            collapsed: HashSet::new(),
+            rows: Vec::new(),
             left_state,
             note_state: ListState::default(),
             filter: String::new(),
@@ -95,8 +311,34 @@ impl App {
             focus: Focus::Left,
             catalog: Catalog::Tags,
             override_state: None,
+            stats: None,
+            snapshots: None,
+            history: History::default(),
+            detail_blame: None,
+            show_context: false,
+            context: None,
+            context_scroll: 0,
+            keymap: Keymap::default(),
+            compare: String::new(),
+            pending: Vec::new(),
+            find: String::new(),
+            find_origin: None,
+            session: Session::default(),
+            panes: Panes::default(),
+            drag: None,
+            input: String::new(),
+            marked: BTreeSet::new(),
+            stdout: None,
+            views: None,
+            views_selected: 0,
+            palette: String::new(),
+            palette_selected: 0,
+            recall: None,
+            draft: String::new(),
+            undo: Vec::new(),
             status: String::new(),
         };
+        app.refresh_tag_counts();
         app.reset_note_selection();
         app
     }
@@ -105,6 +347,7 @@ impl App {
         match self.catalog {
             Catalog::Tags => &self.tags,
             Catalog::Glossary => self.terms.as_deref().unwrap_or(&[]),
+            Catalog::Files => self.files.as_ref().map_or(&[], |t| &t.keys),
         }
     }
 
@@ -116,11 +359,53 @@ impl App {
 
     fn filtered_left(&self) -> Vec<&str> {
         let q = normalize_tag_filter(&self.filter);
-        self.left_keys()
+        let mut keys: Vec<&str> = self
+            .left_keys()
             .iter()
             .map(|s| s.as_str())
             .filter(|t| q.is_empty() || normalize_tag_filter(t).contains(&q))
-            .collect()
+            .collect();
+        if self.catalog == Catalog::Tags && self.session.tags_by_count {
+            // Stable, so equal counts stay by name.
+            let counts = &self.tag_counts;
+            keys.sort_by_key(|t| std::cmp::Reverse(counts.get(*t).copied().unwrap_or(0)));
+        }
+        keys
+    }
+
+    /// Put the left cursor back on `key`, e.g. after the list reorders.
+    fn select_left_key(&mut self, key: &str) {
+        let at = self.filtered_left().iter().position(|k| *k == key);
+        if at.is_some() {
+            self.left_state.select(at);
+        }
+    }
+
+    /// Recount after the tag selection or the notes change.
+    fn refresh_tag_counts(&mut self) {
+        self.tag_counts = self.count_tags();
+    }
+
+    /// Notes each tag would leave in the pane if it were picked too.
+    fn count_tags(&self) -> HashMap<String, usize> {
+        let (tags, exclude) = self.tag_selection();
+        let shown = self.store.select_tags(&tags, self.any_tag, &exclude);
+        if !self.any_tag || tags.is_empty() {
+            return self.store.tag_counts(&shown);
+        }
+        // Any-mode: a pick adds its notes to those already shown.
+        let ids: HashSet<*const Note> = shown.iter().map(|n| *n as *const Note).collect();
+        let rest: Vec<&Note> = self
+            .store
+            .select_tags(&[], false, &exclude)
+            .into_iter()
+            .filter(|n| !ids.contains(&(*n as *const Note)))
+            .collect();
+        let mut counts = self.store.tag_counts(&rest);
+        for tag in &self.tags {
+            *counts.entry(tag.clone()).or_default() += shown.len();
+        }
+        counts
     }
 
     fn selected_left(&self) -> Option<String> {
@@ -135,16 +420,99 @@ impl App {
             return state.ids.iter().filter_map(|&id| self.store.get(id)).collect();
         }
         match self.catalog {
-            Catalog::Tags => self
-                .store
-                .search_tags(&self.selected.iter().cloned().collect::<Vec<_>>()),
+            Catalog::Tags => {
+                let (tags, exclude) = self.tag_selection();
+                self.store.select_tags(&tags, self.any_tag, &exclude)
+            }
             Catalog::Glossary => match self.selected_left() {
                 Some(key) => self.store.define(&key),
                 None => Vec::new(),
             },
+            Catalog::Files => match self.selected_left() {
+                Some(key) => self
+                    .store
+                    .notes()
+                    .iter()
+                    .filter(|n| normalize_path(&n.path).starts_with(&key))
+                    .collect(),
+                None => Vec::new(),
+            },
+        }
+    }
+
+    /// The notes pane: `current_notes`, under file headers when grouped.
+    fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    /// Rebuild `rows` after the selection, grouping, folding or store changed.
+    fn refresh_rows(&mut self) {
+        let notes = self.current_notes();
+        let ids: HashMap<*const Note, usize> = self
+            .store
+            .notes()
+            .iter()
+            .enumerate()
+            .map(|(id, n)| (n as *const Note, id))
+            .collect();
+        let row = |n: &Note| Row::Note(ids[&(n as *const Note)]);
+        let rows = if self.group_files {
+            let mut rows = Vec::new();
+            for group in listing::group(&notes, GroupBy::File) {
+                let collapsed = self.collapsed.contains(&group.title);
+                rows.push(Row::File {
+                    notes: group.notes.len(),
+                    title: group.title,
+                });
+                if !collapsed {
+                    rows.extend(group.notes.into_iter().map(row));
+                }
+            }
+            rows
+        } else {
+            notes.into_iter().map(row).collect()
+        };
+        self.rows = rows;
+    }
+
+    fn toggle_grouping(&mut self) {
+        let selected = self.selected_id();
+        self.group_files = !self.group_files;
+        self.refresh_rows();
+        // Keep the same note selected across the switch.
+        let at = selected.and_then(|id| {
+            self.rows()
+                .iter()
+                .position(|r| matches!(r, Row::Note(n) if *n == id))
+        });
+        match at {
+            Some(i) => self.note_state.select(Some(i)),
+            None => self.reset_note_selection(),
+        }
+    }
+
+    /// Enter: a file header folds or unfolds; a note opens in detail.
+    fn activate(&mut self) {
+        let row = self.note_state.selected().and_then(|i| self.rows().get(i));
+        let header = match row {
+            Some(Row::File { title, .. }) => title.clone(),
+            _ => return self.open_detail(),
+        };
+        if !self.collapsed.remove(&header) {
+            self.collapsed.insert(header);
         }
+        self.refresh_rows();
+    }
+
+    /// Picked and excluded tags, sorted.
+    fn tag_selection(&self) -> (Vec<String>, Vec<String>) {
+        (
+            self.selected.iter().cloned().collect(),
+            self.excluded.iter().cloned().collect(),
+        )
     }
 
+    /// Space: pick the tag under the cursor, or unpick (or un-exclude) it.
     fn toggle_tag(&mut self) {
         if self.catalog != Catalog::Tags || self.focus != Focus::Left {
             return;
@@ -152,21 +520,73 @@ impl App {
         let Some(tag) = self.selected_left() else {
             return;
         };
-        if !self.selected.remove(&tag) {
-            self.selected.insert(tag);
+        if !self.excluded.remove(&tag) && !self.selected.remove(&tag) {
+            self.selected.insert(tag.clone());
         }
+        self.refresh_tag_counts();
         self.override_state = None;
+        self.select_left_key(&tag);
         self.reset_note_selection();
     }
 
-    fn reset_note_selection(&mut self) {
-        if self.current_notes().is_empty() {
-            self.note_state.select(None);
+    /// `!`: leave out notes with the tag under the cursor, or stop to.
+    fn exclude_tag(&mut self) {
+        if self.catalog != Catalog::Tags || self.focus != Focus::Left {
+            return;
+        }
+        let Some(tag) = self.selected_left() else {
+            return;
+        };
+        if !self.excluded.remove(&tag) {
+            self.selected.remove(&tag);
+            self.excluded.insert(tag.clone());
+        }
+        self.refresh_tag_counts();
+        self.override_state = None;
+        self.select_left_key(&tag);
+        self.reset_note_selection();
+    }
+
+    fn toggle_tag_mode(&mut self) {
+        if self.catalog != Catalog::Tags {
+            return;
+        }
+        let at = self.selected_left();
+        self.any_tag = !self.any_tag;
+        self.refresh_tag_counts();
+        self.status = if self.any_tag {
+            "notes need any picked tag".into()
         } else {
-            self.note_state.select(Some(0));
+            "notes need every picked tag".into()
+        };
+        self.override_state = None;
+        if let Some(tag) = at {
+            self.select_left_key(&tag);
+        }
+        self.reset_note_selection();
+    }
+
+    fn toggle_tag_sort(&mut self) {
+        if self.catalog != Catalog::Tags {
+            return;
+        }
+        let at = self.selected_left();
+        self.session.tags_by_count = !self.session.tags_by_count;
+        if let Some(tag) = at {
+            self.select_left_key(&tag);
         }
     }
 
+    /// Rebuild the notes pane and select its first note (past any file
+    /// header).
+    fn reset_note_selection(&mut self) {
+        self.refresh_rows();
+        let rows = self.rows();
+        let first = rows.iter().position(|r| matches!(r, Row::Note(_)));
+        self.note_state
+            .select(first.or((!rows.is_empty()).then_some(0)));
+    }
+
     fn reset_left_selection(&mut self) {
         if self.filtered_left().is_empty() {
             self.left_state.select(None);
@@ -210,7 +630,7 @@ impl App {
     }
 
     fn select_next_note(&mut self) {
-        let len = self.current_notes().len();
+        let len = self.rows().len();
         if len == 0 {
             return;
         }
@@ -223,7 +643,7 @@ impl App {
     }
 
     fn select_prev_note(&mut self) {
-        let len = self.current_notes().len();
+        let len = self.rows().len();
         if len == 0 {
             return;
         }
@@ -256,15 +676,139 @@ impl App {
         };
     }
 
+    /// Rows the focused pane shows inside its border.
+    fn page(&self) -> isize {
+        let area = match self.focus {
+            Focus::Left => self.panes.left,
+            Focus::Notes => self.panes.notes,
+        };
+        area.height.saturating_sub(2).max(1) as isize
+    }
+
+    /// Move the focused pane's selection by `delta` rows, stopping at the
+    /// first and last rather than wrapping like `j` / `k`.
+    fn jump(&mut self, delta: isize) {
+        let len = match self.focus {
+            Focus::Left => self.filtered_left().len(),
+            Focus::Notes => self.rows().len(),
+        };
+        if len == 0 {
+            return;
+        }
+        let state = match self.focus {
+            Focus::Left => &mut self.left_state,
+            Focus::Notes => &mut self.note_state,
+        };
+        let current = state.selected();
+        let at = current
+            .unwrap_or(0)
+            .saturating_add_signed(delta)
+            .min(len - 1);
+        if current == Some(at) {
+            return;
+        }
+        state.select(Some(at));
+        if self.focus == Focus::Left {
+            self.override_state = None;
+            self.reset_note_selection();
+        }
+    }
+
+    /// Rows whose note contains the find query, ignoring case.
+    fn find_hits(&self) -> Vec<usize> {
+        let query = self.find.to_lowercase();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        self.rows()
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| match row {
+                Row::Note(id) => self.store.notes()[*id]
+                    .search_text()
+                    .to_lowercase()
+                    .contains(&query),
+                Row::File { .. } => false,
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Select the first hit at or after row `from` (at or before it when
+    /// going back), wrapping round the list; false if nothing matches.
+    fn find_from(&mut self, from: usize, forward: bool) -> bool {
+        let hits = self.find_hits();
+        let hit = if forward {
+            hits.iter().find(|&&i| i >= from).or(hits.first())
+        } else {
+            hits.iter().rev().find(|&&i| i <= from).or(hits.last())
+        };
+        match hit {
+            Some(&i) => {
+                self.note_state.select(Some(i));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn open_find(&mut self) {
+        self.mode = Mode::Find;
+        self.focus = Focus::Notes;
+        self.find.clear();
+        self.find_origin = self.note_state.selected();
+        self.status.clear();
+    }
+
+    /// Typing in the find prompt: the first hit from where it opened.
+    fn on_find_changed(&mut self) {
+        if !self.find_from(self.find_origin.unwrap_or(0), true) {
+            self.note_state.select(self.find_origin);
+        }
+    }
+
+    /// `n` / `N`: the next or previous hit of the last find.
+    fn find_again(&mut self, forward: bool) {
+        if self.find.is_empty() {
+            self.status = format!(
+                "nothing to find; {} starts one",
+                self.keymap.key(Action::Find)
+            );
+            return;
+        }
+        self.focus = Focus::Notes;
+        let at = self.note_state.selected().unwrap_or(0);
+        let from = if forward { at + 1 } else { at.wrapping_sub(1) };
+        if !self.find_from(from, forward) {
+            self.status = format!("no match for {:?}", self.find);
+            return;
+        }
+        let hits = self.find_hits();
+        let selected = self.note_state.selected();
+        let n = hits
+            .iter()
+            .position(|&i| Some(i) == selected)
+            .map_or(0, |n| n + 1);
+        self.status = format!("find {:?}: {n}/{}", self.find, hits.len());
+    }
+
     fn run_fts(&mut self) {
         let q = self.fts_query.trim();
         if q.is_empty() {
             self.override_state = None;
         } else {
-            self.override_state = Some(Override {
-                kind: OverrideKind::Fts,
-                ids: self.fts.search_ids(q),
-            });
+            match self.fts.query_ids(&self.store, q) {
+                Ok(ids) => {
+                    self.override_state = Some(Override {
+                        kind: OverrideKind::Fts,
+                        ids,
+                    });
+                }
+                Err(e) => {
+                    self.status = e.to_string();
+                    return;
+                }
+            }
         }
         self.focus = Focus::Notes;
         self.reset_note_selection();
@@ -317,6 +861,46 @@ impl App {
         self.status.clear();
     }
 
+    /// The glossary term under the cursor, or the selected definition's.
+    fn selected_term(&self) -> Option<String> {
+        if self.catalog == Catalog::Glossary && self.focus == Focus::Left {
+            return self.selected_left();
+        }
+        match &self.selected_note()?.kind {
+            Kind::Define { term } => Some(term.clone()),
+            _ => None,
+        }
+    }
+
+    fn open_compare(&mut self) {
+        let Some(term) = self.selected_term() else {
+            self.status = "no term selected".into();
+            return;
+        };
+        match self.store.define(&term).len() {
+            0 => self.status = format!("{term} has no definitions"),
+            1 => self.status = format!("{term} has one definition"),
+            _ => {
+                self.compare = term;
+                self.mode = Mode::Compare { scroll: 0 };
+                self.status.clear();
+            }
+        }
+    }
+
+    fn show_files(&mut self) {
+        if self.files.is_none() {
+            self.files = Some(FileTree::build(&self.store));
+        }
+        self.catalog = Catalog::Files;
+        self.filter.clear();
+        self.fts_query.clear();
+        self.override_state = None;
+        self.focus = Focus::Left;
+        self.reset_left_selection();
+        self.status.clear();
+    }
+
     fn show_tags(&mut self) {
         self.catalog = Catalog::Tags;
         self.filter.clear();
@@ -327,17 +911,208 @@ impl App {
         self.status.clear();
     }
 
+    /// Store id of the selected note.
+    fn selected_id(&self) -> Option<usize> {
+        match self.rows().get(self.note_state.selected()?)? {
+            Row::Note(id) => Some(*id),
+            Row::File { .. } => None,
+        }
+    }
+
+    /// Source around the selected note, read when the selection changes.
+    fn ensure_context(&mut self) {
+        let Some(id) = self.selected_id() else {
+            return;
+        };
+        if self.context.as_ref().map(|(i, _)| *i) != Some(id) {
+            self.context_scroll = 0;
+            self.context = match SourceContext::load(&self.store.notes()[id]) {
+                Ok(context) => Some((id, context)),
+                Err(e) => {
+                    tracing::debug!(error = %e, "no source context");
+                    None
+                }
+            };
+        }
+    }
+
+    fn scroll_context(&mut self, delta: isize) {
+        self.context_scroll = self.context_scroll.saturating_add(delta);
+    }
+
+    /// Mark the selected FIXME / TODO done, or open again, in its source file.
+    fn toggle_status(&mut self) {
+        let Some(id) = self.selected_id() else {
+            self.status = "nothing selected".into();
+            return;
+        };
+        let note = &self.store.notes()[id];
+        if !note.is_fixme() && !note.is_todo() {
+            self.status = "only FIXME and TODO notes have a status".into();
+            return;
+        }
+        let status = if note.is_open() {
+            Status::Done
+        } else {
+            Status::Open
+        };
+        let edit = writeback::set_status(note, status);
+        self.record_edit(id, edit);
+    }
+
+    fn open_edit(&mut self, action: EditAction) {
+        let Some(note) = self.selected_note() else {
+            self.status = "nothing selected".into();
+            return;
+        };
+        self.input = match action {
+            EditAction::AddTag => String::new(),
+            EditAction::RemoveTag => match note.tags.as_slice() {
+                [] => {
+                    self.status = "note has no tags".into();
+                    return;
+                }
+                [only] => only.clone(),
+                _ => String::new(),
+            },
+            EditAction::Text => note.text.clone(),
+        };
+        self.mode = Mode::Edit(action);
+        self.status.clear();
+    }
+
+    fn submit_edit(&mut self, action: EditAction) {
+        self.mode = Mode::Browse;
+        let Some(id) = self.selected_id() else {
+            return;
+        };
+        let note = &self.store.notes()[id];
+        let input = self.input.trim();
+        let tag = input.trim_start_matches('#');
+        if writeback::has_delimiter(input) {
+            self.status = "notes cannot contain <!-- or -->".into();
+            return;
+        }
+        let edit = match action {
+            EditAction::AddTag | EditAction::RemoveTag if tag.is_empty() => return,
+            EditAction::AddTag | EditAction::RemoveTag if tag.contains(char::is_whitespace) => {
+                self.status = "tags cannot contain spaces".into();
+                return;
+            }
+            EditAction::AddTag if note.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) => {
+                self.status = format!("note already has #{tag}");
+                return;
+            }
+            EditAction::AddTag => writeback::add_tag(note, tag),
+            EditAction::RemoveTag => writeback::remove_tag(note, tag),
+            EditAction::Text if input == note.text => return,
+            EditAction::Text => writeback::set_text(note, input),
+        };
+        self.record_edit(id, edit);
+    }
+
+    /// Apply a write-back and remember how to undo it.
+    fn record_edit(&mut self, id: usize, edit: crate::Result<writeback::Edit>) {
+        match edit {
+            Ok(edit) => {
+                self.undo.push(Undo {
+                    id,
+                    previous: edit.previous.clone(),
+                });
+                self.apply_edit(id, edit);
+            }
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+
+    fn undo_edit(&mut self) {
+        let Some(undo) = self.undo.pop() else {
+            self.status = "nothing to undo".into();
+            return;
+        };
+        let note = &self.store.notes()[undo.id];
+        match writeback::restore(note, &undo.previous) {
+            Ok(edit) => {
+                self.apply_edit(undo.id, edit);
+                self.status = format!("undone: {}", self.status);
+            }
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+
+    /// Bring the store, full-text index, and tag list up to date with a
+    /// comment that was just rewritten.
+    fn apply_edit(&mut self, id: usize, edit: writeback::Edit) {
+        let old = self.store.notes()[id].clone();
+        self.store.shift_lines(&old.path, old.line, edit.line_delta);
+        self.fts.update(id, &old, &edit.note);
+        self.status = format!(
+            "{}:{} {}",
+            edit.note.path.display(),
+            edit.note.line,
+            edit_summary(&old, &edit.note)
+        );
+        self.store.replace(id, edit.note);
+        self.context = None;
+        self.tags = self.store.tags().into_iter().map(str::to_owned).collect();
+        self.refresh_tag_counts();
+        self.stats = None;
+        self.refresh_rows();
+        let (left, notes) = (self.filtered_left().len(), self.rows().len());
+        clamp(&mut self.left_state, left);
+        clamp(&mut self.note_state, notes);
+    }
+
     fn selected_note(&self) -> Option<&Note> {
-        let notes = self.current_notes();
-        self.note_state
-            .selected()
-            .and_then(|i| notes.get(i).copied())
+        self.store.get(self.selected_id()?)
     }
 
     fn open_detail(&mut self) {
-        if self.selected_note().is_some() {
-            self.mode = Mode::Detail { scroll: 0 };
-            self.status.clear();
+        let Some(note) = self.selected_note().cloned() else {
+            return;
+        };
+        self.detail_blame = match self.history.blame(&note) {
+            Ok(blame) => blame.cloned(),
+            Err(e) => {
+                tracing::debug!(error = %e, "no blame for detail view");
+                None
+            }
+        };
+        self.mode = Mode::Detail {
+            scroll: 0,
+            source: false,
+        };
+        self.status.clear();
+    }
+
+    fn open_stats(&mut self) {
+        if self.stats.is_none() {
+            self.stats = Some(Stats::collect(&self.store, STATS_TOP_TAGS));
+        }
+        self.mode = Mode::Stats { scroll: 0 };
+        self.status.clear();
+    }
+
+    fn open_progress(&mut self) {
+        if self.snapshots.is_none() {
+            match progress::load() {
+                Ok(snapshots) => self.snapshots = Some(snapshots),
+                Err(e) => {
+                    self.status = format!("progress: {e}");
+                    return;
+                }
+            }
+        }
+        self.mode = Mode::Progress;
+        self.status.clear();
+    }
+
+    /// Yank the marked notes, or the selected one if none are marked.
+    fn yank(&mut self) {
+        if self.marked.is_empty() {
+            self.yank_selected();
+        } else {
+            self.export(ExportFormat::Text, "");
         }
     }
 
@@ -353,32 +1128,703 @@ impl App {
         }
     }
 
-    fn on_filter_changed(&mut self) {
-        self.reset_left_selection();
+    /// Mark or unmark the selected note, then move to the next row.
+    fn toggle_mark(&mut self) {
+        let Some(id) = self.selected_id() else {
+            return;
+        };
+        if !self.marked.remove(&id) {
+            self.marked.insert(id);
+        }
+        self.select_next_note();
     }
 
-    fn left_label(&self) -> &'static str {
-        match self.catalog {
-            Catalog::Tags => "tags",
-            Catalog::Glossary => "terms",
+    /// Mark every note in the pane, or unmark them if all already are.
+    fn mark_all(&mut self) {
+        let current = self.current_notes();
+        let ids: Vec<usize> = self
+            .store
+            .notes()
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| current.iter().any(|c| std::ptr::eq(*c, *n)))
+            .map(|(id, _)| id)
+            .collect();
+        if ids.iter().all(|id| self.marked.contains(id)) {
+            for id in &ids {
+                self.marked.remove(id);
+            }
+        } else {
+            self.marked.extend(ids);
         }
+        self.status = format!("{} marked", self.marked.len());
     }
-}
 
-pub fn run(store: NoteStore) -> crate::Result<()> {
+    /// Marked notes in scan order, or else the selected note.
+    fn batch(&self) -> Vec<&Note> {
+        if self.marked.is_empty() {
+            return self.selected_note().into_iter().collect();
+        }
+        self.marked
+            .iter()
+            .filter_map(|&id| self.store.get(id))
+            .collect()
+    }
+
+    /// Send the batch to the clipboard (empty `to`), stdout after exit
+    /// (`-`), or a file.
+    fn export(&mut self, format: ExportFormat, to: &str) {
+        let notes = self.batch();
+        let count = notes.len();
+        let text = match export::render(&notes, format) {
+            Ok(text) => text,
+            Err(e) => {
+                self.status = format!("export failed: {e}");
+                return;
+            }
+        };
+        let noun = if count == 1 { "note" } else { "notes" };
+        self.status = match to.trim() {
+            "" => match Clipboard::new().and_then(|mut c| c.set_text(text)) {
+                Ok(()) => format!("copied {count} {noun} as {}", format.label()),
+                Err(e) => format!("copy failed: {e}"),
+            },
+            "-" => {
+                self.stdout = Some(text);
+                format!("{count} {noun} print as {} on exit", format.label())
+            }
+            // Never over an existing file: it may well be a chapter.
+            path => match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path)
+                .and_then(|mut file| io::Write::write_all(&mut file, text.as_bytes()))
+            {
+                Ok(()) => format!("wrote {count} {noun} to {path}"),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    format!("{path} exists; export to a new file")
+                }
+                Err(e) => format!("export to {path} failed: {e}"),
+            },
+        };
+    }
+
+    fn on_filter_changed(&mut self) {
+        self.reset_left_selection();
+    }
+
+    /// Run a keymap action in the current mode; returns true to quit.
+    fn on_action(&mut self, action: Action) -> bool {
+        match self.mode {
+            Mode::Browse => return self.on_browse_action(action),
+            Mode::Detail { scroll, source } => match action {
+                Action::Back | Action::Quit | Action::Open => {
+                    self.mode = Mode::Browse;
+                    self.status.clear();
+                }
+                Action::Down if source => self.scroll_context(1),
+                Action::Up if source => self.scroll_context(-1),
+                Action::Down => {
+                    self.mode = Mode::Detail {
+                        scroll: scroll.saturating_add(1),
+                        source,
+                    };
+                }
+                Action::Up => {
+                    self.mode = Mode::Detail {
+                        scroll: scroll.saturating_sub(1),
+                        source,
+                    };
+                }
+                Action::Source => {
+                    self.mode = Mode::Detail {
+                        scroll,
+                        source: !source,
+                    };
+                }
+                Action::Yank => self.yank_selected(),
+                Action::ToggleStatus => self.toggle_status(),
+                _ => {}
+            },
+            Mode::Stats { scroll } => match action {
+                Action::Back | Action::Quit | Action::Stats => self.mode = Mode::Browse,
+                Action::Down => {
+                    self.mode = Mode::Stats {
+                        scroll: scroll.saturating_add(1),
+                    };
+                }
+                Action::Up => {
+                    self.mode = Mode::Stats {
+                        scroll: scroll.saturating_sub(1),
+                    };
+                }
+                _ => {}
+            },
+            Mode::Compare { scroll } => match action {
+                Action::Back | Action::Quit | Action::Open | Action::Compare => {
+                    self.mode = Mode::Browse;
+                }
+                Action::Down => {
+                    self.mode = Mode::Compare {
+                        scroll: scroll.saturating_add(1),
+                    };
+                }
+                Action::Up => {
+                    self.mode = Mode::Compare {
+                        scroll: scroll.saturating_sub(1),
+                    };
+                }
+                _ => {}
+            },
+            Mode::Progress => {
+                if matches!(action, Action::Back | Action::Quit | Action::Progress) {
+                    self.mode = Mode::Browse;
+                }
+            }
+            Mode::Help { scroll } => match action {
+                Action::Down => {
+                    self.mode = Mode::Help {
+                        scroll: scroll.saturating_add(1),
+                    };
+                }
+                Action::Up => {
+                    self.mode = Mode::Help {
+                        scroll: scroll.saturating_sub(1),
+                    };
+                }
+                // Rendering clamps the scroll to the last page.
+                Action::Top => self.mode = Mode::Help { scroll: 0 },
+                Action::Bottom => self.mode = Mode::Help { scroll: u16::MAX },
+                _ => self.mode = Mode::Browse,
+            },
+            Mode::Views => match action {
+                Action::Back | Action::Quit | Action::Views => self.mode = Mode::Browse,
+                Action::Open => {
+                    self.mode = Mode::Browse;
+                    if let Some((name, view)) = self.selected_view() {
+                        self.apply_view(&view);
+                        self.status = format!("view {name}");
+                    }
+                }
+                Action::Up => self.views_selected = self.views_selected.saturating_sub(1),
+                Action::Down => {
+                    let last = self.views.as_ref().map_or(0, |v| v.len().saturating_sub(1));
+                    self.views_selected = (self.views_selected + 1).min(last);
+                }
+                Action::DeleteView => self.delete_view(),
+                _ => {}
+            },
+            Mode::Filter
+            | Mode::Fts
+            | Mode::Find
+            | Mode::Edit(_)
+            | Mode::Palette
+            | Mode::Export(_)
+            | Mode::SaveView => {}
+        }
+        false
+    }
+
+    fn on_browse_action(&mut self, action: Action) -> bool {
+        match action {
+            Action::Quit => return true,
+            Action::Help => self.mode = Mode::Help { scroll: 0 },
+            Action::Back => {
+                if !self.marked.is_empty() {
+                    self.marked.clear();
+                } else if self.override_state.is_some() {
+                    self.override_state = None;
+                    self.fts_query.clear();
+                    self.reset_note_selection();
+                } else if self.catalog != Catalog::Tags {
+                    self.show_tags();
+                } else {
+                    return true;
+                }
+            }
+            Action::Filter => {
+                self.mode = Mode::Filter;
+                self.filter.clear();
+                self.refresh_rows();
+                self.recall = None;
+                self.status.clear();
+            }
+            Action::Fts => {
+                self.mode = Mode::Fts;
+                self.fts_query.clear();
+                self.recall = None;
+                self.status.clear();
+            }
+            Action::Views => self.open_views(),
+            Action::SaveView => {
+                if self.ensure_views() {
+                    self.mode = Mode::SaveView;
+                    self.input.clear();
+                    self.status.clear();
+                }
+            }
+            Action::Palette => {
+                self.mode = Mode::Palette;
+                self.palette.clear();
+                self.palette_selected = 0;
+                self.status.clear();
+            }
+            Action::Down => self.move_down(),
+            Action::Up => self.move_up(),
+            Action::Top => self.jump(isize::MIN),
+            Action::Bottom => self.jump(isize::MAX),
+            Action::PageDown => self.jump(self.page()),
+            Action::PageUp => self.jump(-self.page()),
+            Action::HalfDown => self.jump((self.page() / 2).max(1)),
+            Action::HalfUp => self.jump(-(self.page() / 2).max(1)),
+            Action::Find => self.open_find(),
+            Action::FindNext => self.find_again(true),
+            Action::FindPrev => self.find_again(false),
+            Action::ToggleTag => self.toggle_tag(),
+            Action::ExcludeTag => self.exclude_tag(),
+            Action::TagMode => self.toggle_tag_mode(),
+            Action::TagSort => self.toggle_tag_sort(),
+            Action::Focus => self.toggle_focus(),
+            Action::Open => self.activate(),
+            Action::Yank => self.yank(),
+            Action::Mark => self.toggle_mark(),
+            Action::MarkAll => self.mark_all(),
+            Action::Export => {
+                if self.batch().is_empty() {
+                    self.status = "nothing to export".into();
+                } else {
+                    self.mode = Mode::Export(ExportFormat::default());
+                    self.input.clear();
+                    self.status.clear();
+                }
+            }
+            Action::ToggleStatus => self.toggle_status(),
+            Action::AddTag => self.open_edit(EditAction::AddTag),
+            Action::RemoveTag => self.open_edit(EditAction::RemoveTag),
+            Action::EditText => self.open_edit(EditAction::Text),
+            Action::Undo => self.undo_edit(),
+            Action::Errata => self.show_errata(),
+            Action::Pending => self.show_pending(),
+            Action::Stats => self.open_stats(),
+            Action::Progress => self.open_progress(),
+            Action::Glossary => match self.catalog {
+                Catalog::Glossary => self.show_tags(),
+                Catalog::Tags | Catalog::Files => self.show_glossary(),
+            },
+            Action::Compare => self.open_compare(),
+            Action::Files => match self.catalog {
+                Catalog::Files => self.show_tags(),
+                Catalog::Tags | Catalog::Glossary => self.show_files(),
+            },
+            Action::Group => self.toggle_grouping(),
+            Action::Source => self.show_context = !self.show_context,
+            Action::SourceDown if self.show_context => self.scroll_context(1),
+            Action::SourceUp if self.show_context => self.scroll_context(-1),
+            // Delete only means something in the views list.
+            Action::SourceDown | Action::SourceUp | Action::DeleteView => {}
+            Action::Narrow => self.resize_left(-2),
+            Action::Widen => self.resize_left(2),
+        }
+        false
+    }
+
+    /// The selection as a saveable view.
+    fn current_view(&self) -> View {
+        let list = self.override_state.as_ref().map(|o| o.kind);
+        let (tags, exclude) = match self.catalog {
+            Catalog::Tags => self.tag_selection(),
+            Catalog::Glossary | Catalog::Files => Default::default(),
+        };
+        View {
+            catalog: self.catalog,
+            tags,
+            any: self.catalog == Catalog::Tags && self.any_tag,
+            exclude,
+            key: match self.catalog {
+                Catalog::Tags => None,
+                Catalog::Glossary | Catalog::Files => self.selected_left(),
+            },
+            list,
+            query: match list {
+                Some(OverrideKind::Fts) => self.fts_query.clone(),
+                _ => String::new(),
+            },
+        }
+    }
+
+    fn apply_view(&mut self, view: &View) {
+        match view.catalog {
+            Catalog::Tags => self.show_tags(),
+            Catalog::Glossary => self.show_glossary(),
+            Catalog::Files => self.show_files(),
+        }
+        if view.catalog == Catalog::Tags {
+            // Tags gone since the view was saved are dropped.
+            let known = |t: &&String| self.tags.contains(t);
+            self.selected = view.tags.iter().filter(known).cloned().collect();
+            self.excluded = view.exclude.iter().filter(known).cloned().collect();
+            self.any_tag = view.any;
+            self.refresh_tag_counts();
+        }
+        if let Some(key) = &view.key {
+            self.select_left_key(key);
+        }
+        match view.list {
+            Some(OverrideKind::Errata) => self.show_errata(),
+            Some(OverrideKind::Pending) => self.show_pending(),
+            Some(OverrideKind::Fts) => {
+                self.fts_query = view.query.clone();
+                self.run_fts();
+            }
+            None => {}
+        }
+        self.reset_note_selection();
+    }
+
+    /// Read saved views once; false (with a status) if they can't be read.
+    fn ensure_views(&mut self) -> bool {
+        if self.views.is_none() {
+            match view::load() {
+                Ok(views) => self.views = Some(views),
+                Err(e) => {
+                    self.status = format!("views: {e}");
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    fn open_views(&mut self) {
+        if !self.ensure_views() {
+            return;
+        }
+        if self.views.as_ref().is_some_and(BTreeMap::is_empty) {
+            self.status = format!(
+                "no saved views; {} saves one",
+                self.keymap.key(Action::SaveView)
+            );
+            return;
+        }
+        self.views_selected = 0;
+        self.mode = Mode::Views;
+        self.status.clear();
+    }
+
+    fn selected_view(&self) -> Option<(String, View)> {
+        let (name, view) = self.views.as_ref()?.iter().nth(self.views_selected)?;
+        Some((name.clone(), view.clone()))
+    }
+
+    fn save_view(&mut self) {
+        let name = self.input.trim().to_owned();
+        self.mode = Mode::Browse;
+        if name.is_empty() {
+            return;
+        }
+        let view = self.current_view();
+        match view::save(&name, view.clone()) {
+            Ok(()) => {
+                self.status = format!("saved view {name}: {}", view.describe());
+                self.views
+                    .get_or_insert_with(BTreeMap::new)
+                    .insert(name, view);
+            }
+            Err(e) => self.status = format!("views: {e}"),
+        }
+    }
+
+    fn delete_view(&mut self) {
+        let Some((name, _)) = self.selected_view() else {
+            return;
+        };
+        match view::remove(&name) {
+            Ok(_) => {
+                let views = self.views.get_or_insert_with(BTreeMap::new);
+                views.remove(&name);
+                self.views_selected = self.views_selected.min(views.len().saturating_sub(1));
+                if views.is_empty() {
+                    self.mode = Mode::Browse;
+                }
+                self.status = format!("deleted view {name}");
+            }
+            Err(e) => self.status = format!("views: {e}"),
+        }
+    }
+
+    /// Step through the open prompt's history; stepping past the newest
+    /// entry brings back what was typed before.
+    fn recall(&mut self, older: bool) {
+        let (history, text) = match self.mode {
+            Mode::Filter => (&self.session.filter_history, &mut self.filter),
+            Mode::Fts => (&self.session.fts_history, &mut self.fts_query),
+            _ => return,
+        };
+        let next = match (self.recall, older) {
+            (None, true) if !history.is_empty() => {
+                self.draft = text.clone();
+                Some(history.len() - 1)
+            }
+            (None, _) => return,
+            (Some(i), true) => Some(i.saturating_sub(1)),
+            (Some(i), false) => (i + 1 < history.len()).then_some(i + 1),
+        };
+        *text = match next {
+            Some(i) => history[i].clone(),
+            None => std::mem::take(&mut self.draft),
+        };
+        self.recall = next;
+        if matches!(self.mode, Mode::Filter) {
+            self.on_filter_changed();
+        }
+    }
+
+    /// Actions matching the palette query, best first.
+    fn palette_matches(&self) -> Vec<Action> {
+        let mut scored: Vec<(usize, Action)> = Action::ALL
+            .into_iter()
+            .filter(|a| !matches!(a, Action::Palette | Action::DeleteView))
+            .filter_map(|a| {
+                let text = format!("{} {}", a.name().replace('_', " "), a.description());
+                fuzzy_score(&self.palette, &text).map(|score| (score, a))
+            })
+            .collect();
+        // Stable, so ties keep the help order.
+        scored.sort_by_key(|(score, _)| *score);
+        scored.into_iter().map(|(_, a)| a).collect()
+    }
+
+    /// Run the highlighted palette action; returns true to quit.
+    fn run_palette(&mut self) -> bool {
+        let action = self.palette_matches().get(self.palette_selected).copied();
+        self.mode = Mode::Browse;
+        match action {
+            Some(action) => self.on_browse_action(action),
+            None => false,
+        }
+    }
+
+    /// Feed a key through the keymap, holding it while it may start a
+    /// longer binding; returns true to quit.
+    fn on_key(&mut self, key: Key) -> bool {
+        self.pending.push(key);
+        match self.keymap.lookup(&self.pending) {
+            Lookup::Prefix => false,
+            Lookup::Action(action) => {
+                self.pending.clear();
+                self.on_action(action)
+            }
+            // `g` then `j`: `g` runs alone, then `j` starts over.
+            Lookup::None if self.pending.len() > 1 => {
+                self.pending.pop();
+                self.flush_pending() || self.on_key(key)
+            }
+            Lookup::None => {
+                self.pending.clear();
+                if matches!(self.mode, Mode::Help { .. }) {
+                    self.mode = Mode::Browse;
+                }
+                false
+            }
+        }
+    }
+
+    /// Run what the pending keys are bound to on their own (`g` after no
+    /// second `g`); returns true to quit.
+    fn flush_pending(&mut self) -> bool {
+        let keys = std::mem::take(&mut self.pending);
+        match self.keymap.exact(&keys) {
+            Some(action) => self.on_action(action),
+            None => false,
+        }
+    }
+
+    /// Grow (or with a negative `delta`, shrink) the left pane.
+    fn resize_left(&mut self, delta: i16) {
+        let width = self.session.left_width.saturating_add_signed(delta);
+        self.session.left_width = width.clamp(PANE_MIN, PANE_MAX);
+    }
+
+    fn on_mouse(&mut self, mouse: MouseEvent) {
+        let (x, y) = (mouse.column, mouse.row);
+        let down = match mouse.kind {
+            MouseEventKind::ScrollDown => 1,
+            MouseEventKind::ScrollUp => -1,
+            MouseEventKind::Down(MouseButton::Left) => return self.on_click(x, y),
+            MouseEventKind::Drag(MouseButton::Left) => return self.on_drag(x),
+            MouseEventKind::Up(MouseButton::Left) => {
+                self.drag = None;
+                return;
+            }
+            _ => return,
+        };
+        match self.mode {
+            Mode::Browse => {
+                let panes = &self.panes;
+                if contains(panes.left, x, y) {
+                    self.focus = Focus::Left;
+                } else if contains(panes.notes, x, y) {
+                    self.focus = Focus::Notes;
+                } else {
+                    if panes.source.is_some_and(|r| contains(r, x, y)) {
+                        self.scroll_context(down);
+                    }
+                    return;
+                }
+                if down > 0 {
+                    self.move_down();
+                } else {
+                    self.move_up();
+                }
+            }
+            Mode::Detail { scroll, source } => {
+                if source {
+                    self.scroll_context(down);
+                } else {
+                    self.mode = Mode::Detail {
+                        scroll: scroll.saturating_add_signed(down as i16),
+                        source,
+                    };
+                }
+            }
+            Mode::Stats { scroll } => {
+                self.mode = Mode::Stats {
+                    scroll: scroll.saturating_add_signed(down as i16),
+                };
+            }
+            Mode::Compare { scroll } => {
+                self.mode = Mode::Compare {
+                    scroll: scroll.saturating_add_signed(down as i16),
+                };
+            }
+            Mode::Help { scroll } => {
+                self.mode = Mode::Help {
+                    scroll: scroll.saturating_add_signed(down as i16),
+                };
+            }
+            _ => {}
+        }
+    }
+
+    /// Left click: grab a pane border, or select the row under the pointer
+    /// (a tag's `[ ]` box toggles it; a second click on a note opens it).
+    fn on_click(&mut self, x: u16, y: u16) {
+        if !matches!(self.mode, Mode::Browse) {
+            return;
+        }
+        let panes = &self.panes;
+        // Each border only along the rows its two panes span.
+        let on_edge = |right_of: Rect, left_of: Rect| {
+            (x + 1 == right_of.right() && contains(right_of, x, y))
+                || (x == left_of.x && contains(left_of, x, y))
+        };
+        if on_edge(panes.left, panes.notes) {
+            self.drag = Some(Border::Left);
+            return;
+        }
+        if let Some(source) = panes.source {
+            if on_edge(panes.preview, source) {
+                self.drag = Some(Border::Source);
+                return;
+            }
+        }
+        if let Some(i) = list_row(panes.left, self.left_state.offset(), x, y) {
+            let checkbox = x < panes.left.x + 5;
+            self.focus = Focus::Left;
+            if i >= self.filtered_left().len() {
+                return;
+            }
+            if self.left_state.selected() != Some(i) {
+                self.left_state.select(Some(i));
+                self.override_state = None;
+                self.reset_note_selection();
+            }
+            if checkbox {
+                self.toggle_tag();
+            }
+        } else if let Some(i) = list_row(panes.notes, self.note_state.offset(), x, y) {
+            self.focus = Focus::Notes;
+            if i >= self.rows().len() {
+                return;
+            }
+            if self.note_state.selected() == Some(i) {
+                self.activate();
+            } else {
+                self.note_state.select(Some(i));
+            }
+        }
+    }
+
+    fn on_drag(&mut self, x: u16) {
+        let panes = &self.panes;
+        match self.drag {
+            Some(Border::Left) => {
+                let area = panes.left.union(panes.notes);
+                self.session.left_width = pane_percent((x + 1).saturating_sub(area.x), area.width);
+            }
+            Some(Border::Source) => {
+                let Some(source) = panes.source else {
+                    return;
+                };
+                // The source pane is on the right, so measure from there.
+                let area = panes.preview.union(source);
+                self.session.source_width =
+                    pane_percent(area.right().saturating_sub(x), area.width);
+            }
+            None => {}
+        }
+    }
+
+    fn left_label(&self) -> &'static str {
+        match self.catalog {
+            Catalog::Tags => "tags",
+            Catalog::Glossary => "terms",
+            Catalog::Files => "files",
+        }
+    }
+}
+
+pub fn run(
+    store: NoteStore,
+    analyzer: Analyzer,
+    formatter: Formatter,
+    theme: Theme,
+    keymap: Keymap,
+) -> crate::Result<()> {
+    let saved = session::load().unwrap_or_else(|e| {
+        tracing::warn!(error = %e, "could not read the saved TUI layout");
+        Session::default()
+    });
+
     enable_raw_mode()?;
     let mut out = stdout();
-    execute!(out, EnterAlternateScreen)?;
+    execute!(out, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(out);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new(store);
+    let mut app = App::new(store, analyzer, formatter, theme);
+    app.keymap = keymap;
+    app.session = saved.clone();
     let result = event_loop(&mut terminal, &mut app);
 
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
     terminal.show_cursor()?;
 
+    if let Some(text) = app.stdout.take() {
+        print!("{text}");
+    }
+
+    if app.session != saved {
+        if let Err(e) = session::save(&app.session) {
+            tracing::warn!(error = %e, "could not save the TUI layout");
+        }
+    }
+
     result
 }
 
@@ -389,53 +1835,23 @@ fn event_loop(
     loop {
         terminal.draw(|f| ui(f, app))?;
 
-        let Event::Key(key) = event::read()? else {
-            continue;
-        };
-        if key.kind != KeyEventKind::Press {
+        if !app.pending.is_empty() && !event::poll(SEQUENCE_TIMEOUT)? {
+            if app.flush_pending() {
+                return Ok(());
+            }
             continue;
         }
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            Event::Mouse(mouse) => {
+                app.on_mouse(mouse);
+                continue;
+            }
+            _ => continue,
+        };
 
+        // Prompts take keys literally; everything else goes through the keymap.
         match &app.mode {
-            Mode::Browse => match key.code {
-                KeyCode::Char('q') => return Ok(()),
-                KeyCode::Char('h') | KeyCode::Char('?') => app.mode = Mode::Help,
-                KeyCode::Esc => {
-                    if app.override_state.is_some() {
-                        app.override_state = None;
-                        app.fts_query.clear();
-                        app.reset_note_selection();
-                    } else if app.catalog == Catalog::Glossary {
-                        app.show_tags();
-                    } else {
-                        return Ok(());
-                    }
-                }
-                KeyCode::Char('/') => {
-                    app.mode = Mode::Filter;
-                    app.filter.clear();
-                    app.status.clear();
-                }
-                KeyCode::Char('f') => {
-                    app.mode = Mode::Fts;
-                    app.fts_query.clear();
-                    app.status.clear();
-                }
-                KeyCode::Char('j') | KeyCode::Down => app.move_down(),
-                KeyCode::Char('k') | KeyCode::Up => app.move_up(),
-                KeyCode::Char(' ') => app.toggle_tag(),
-                KeyCode::Tab => app.toggle_focus(),
-                KeyCode::BackTab => app.toggle_focus(),
-                KeyCode::Enter => app.open_detail(),
-                KeyCode::Char('y') => app.yank_selected(),
-                KeyCode::Char('e') => app.show_errata(),
-                KeyCode::Char('p') => app.show_pending(),
-                KeyCode::Char('g') => match app.catalog {
-                    Catalog::Glossary => app.show_tags(),
-                    Catalog::Tags => app.show_glossary(),
-                },
-                _ => {}
-            },
             Mode::Filter => match key.code {
                 KeyCode::Esc => {
                     app.mode = Mode::Browse;
@@ -444,14 +1860,19 @@ fn event_loop(
                 }
                 KeyCode::Enter => {
                     app.mode = Mode::Browse;
+                    session::remember(&mut app.session.filter_history, &app.filter);
                     app.on_filter_changed();
                 }
+                KeyCode::Up => app.recall(true),
+                KeyCode::Down => app.recall(false),
                 KeyCode::Backspace => {
                     app.filter.pop();
+                    app.recall = None;
                     app.on_filter_changed();
                 }
                 KeyCode::Char(c) => {
                     app.filter.push(c);
+                    app.recall = None;
                     app.on_filter_changed();
                 }
                 _ => {}
@@ -464,39 +1885,100 @@ fn event_loop(
                     app.reset_note_selection();
                 }
                 KeyCode::Enter => {
+                    session::remember(&mut app.session.fts_history, &app.fts_query);
                     app.run_fts();
                     app.mode = Mode::Browse;
                 }
+                KeyCode::Up => app.recall(true),
+                KeyCode::Down => app.recall(false),
                 KeyCode::Backspace => {
                     app.fts_query.pop();
+                    app.recall = None;
                 }
                 KeyCode::Char(c) => {
                     app.fts_query.push(c);
+                    app.recall = None;
                 }
                 _ => {}
             },
-            Mode::Detail { scroll } => {
-                let scroll = *scroll;
+            Mode::Find => match key.code {
+                KeyCode::Esc => {
+                    app.mode = Mode::Browse;
+                    app.find.clear();
+                    app.note_state.select(app.find_origin);
+                }
+                KeyCode::Enter => app.mode = Mode::Browse,
+                KeyCode::Backspace => {
+                    app.find.pop();
+                    app.on_find_changed();
+                }
+                KeyCode::Char(c) => {
+                    app.find.push(c);
+                    app.on_find_changed();
+                }
+                _ => {}
+            },
+            Mode::Export(format) => {
+                let format = *format;
                 match key.code {
-                    KeyCode::Esc | KeyCode::Char('q') | KeyCode::Enter => {
+                    KeyCode::Esc => app.mode = Mode::Browse,
+                    KeyCode::Tab => app.mode = Mode::Export(format.next()),
+                    KeyCode::Enter => {
                         app.mode = Mode::Browse;
-                        app.status.clear();
+                        let to = std::mem::take(&mut app.input);
+                        app.export(format, &to);
                     }
-                    KeyCode::Char('j') | KeyCode::Down => {
-                        app.mode = Mode::Detail {
-                            scroll: scroll.saturating_add(1),
-                        };
+                    KeyCode::Backspace => {
+                        app.input.pop();
                     }
-                    KeyCode::Char('k') | KeyCode::Up => {
-                        app.mode = Mode::Detail {
-                            scroll: scroll.saturating_sub(1),
-                        };
+                    KeyCode::Char(c) => app.input.push(c),
+                    _ => {}
+                }
+            }
+            Mode::SaveView => match key.code {
+                KeyCode::Esc => app.mode = Mode::Browse,
+                KeyCode::Enter => app.save_view(),
+                KeyCode::Backspace => {
+                    app.input.pop();
+                }
+                KeyCode::Char(c) => app.input.push(c),
+                _ => {}
+            },
+            Mode::Palette => match key.code {
+                KeyCode::Esc => app.mode = Mode::Browse,
+                KeyCode::Enter if app.run_palette() => return Ok(()),
+                KeyCode::Up => app.palette_selected = app.palette_selected.saturating_sub(1),
+                KeyCode::Down => {
+                    let last = app.palette_matches().len().saturating_sub(1);
+                    app.palette_selected = (app.palette_selected + 1).min(last);
+                }
+                KeyCode::Backspace => {
+                    app.palette.pop();
+                    app.palette_selected = 0;
+                }
+                KeyCode::Char(c) => {
+                    app.palette.push(c);
+                    app.palette_selected = 0;
+                }
+                _ => {}
+            },
+            Mode::Edit(action) => {
+                let action = *action;
+                match key.code {
+                    KeyCode::Esc => app.mode = Mode::Browse,
+                    KeyCode::Enter => app.submit_edit(action),
+                    KeyCode::Backspace => {
+                        app.input.pop();
                     }
-                    KeyCode::Char('y') => app.yank_selected(),
+                    KeyCode::Char(c) => app.input.push(c),
                     _ => {}
                 }
             }
-            Mode::Help => app.mode = Mode::Browse,
+            _ => {
+                if app.on_key(Key::from_event(&key)) {
+                    return Ok(());
+                }
+            }
         }
     }
 }
@@ -507,7 +1989,11 @@ fn ui(f: &mut Frame, app: &mut App) {
         .constraints([
             Constraint::Length(1),
             Constraint::Min(5),
-            Constraint::Length(4),
+            if app.show_context {
+                Constraint::Percentage(45)
+            } else {
+                Constraint::Length(4)
+            },
         ])
         .split(f.area());
 
@@ -518,7 +2004,11 @@ fn ui(f: &mut Frame, app: &mut App) {
                     Focus::Left => app.left_label(),
                     Focus::Notes => "notes",
                 };
-                format!(" {focus}   h help   q quit ")
+                format!(
+                    " {focus}   {} help   {} quit ",
+                    app.keymap.key(Action::Help),
+                    app.keymap.key(Action::Quit)
+                )
             } else {
                 format!(" {} ", app.status)
             }
@@ -532,40 +2022,254 @@ fn ui(f: &mut Frame, app: &mut App) {
             " full-text: {}_  (Enter search, Esc cancel) ",
             app.fts_query
         ),
+        Mode::Find => format!(
+            " find: {}_  {} matching  (Enter keep, Esc cancel; then {}/{}) ",
+            app.find,
+            app.find_hits().len(),
+            app.keymap.key(Action::FindNext),
+            app.keymap.key(Action::FindPrev)
+        ),
         Mode::Detail { .. } => {
+            let keys = &app.keymap;
+            let hints = format!(
+                "{}/{} scroll  {} body/source  {} yank  {} done/open  {}/{} close",
+                keys.key(Action::Down),
+                keys.key(Action::Up),
+                keys.key(Action::Source),
+                keys.key(Action::Yank),
+                keys.key(Action::ToggleStatus),
+                keys.key(Action::Open),
+                keys.key(Action::Back)
+            );
             if app.status.is_empty() {
-                " j/k scroll  y yank  enter/esc close ".to_string()
+                format!(" {hints} ")
             } else {
-                format!(" {}  |  j/k scroll  y yank  enter/esc close ", app.status)
+                format!(" {}  |  {hints} ", app.status)
             }
         }
-        Mode::Help => " help — any key to close ".to_string(),
+        Mode::Stats { .. } => format!(
+            " {}/{} scroll  {}/{} close ",
+            app.keymap.key(Action::Down),
+            app.keymap.key(Action::Up),
+            app.keymap.key(Action::Stats),
+            app.keymap.key(Action::Back)
+        ),
+        Mode::Compare { .. } => format!(
+            " {}/{} scroll  {}/{} close ",
+            app.keymap.key(Action::Down),
+            app.keymap.key(Action::Up),
+            app.keymap.key(Action::Compare),
+            app.keymap.key(Action::Back)
+        ),
+        Mode::Progress => format!(
+            " {}/{} close ",
+            app.keymap.key(Action::Progress),
+            app.keymap.key(Action::Back)
+        ),
+        Mode::Edit(_) => " enter save to file  esc cancel ".to_string(),
+        Mode::Palette => " enter run  ↑/↓ choose  esc cancel ".to_string(),
+        Mode::Export(format) => format!(
+            " export {} as {} to: {}_  (tab format; enter: empty = clipboard, - = stdout on exit, or a file) ",
+            app.batch().len(),
+            format.label(),
+            app.input
+        ),
+        Mode::Views => format!(
+            " {} apply  {}/{} choose  {} delete  {} close ",
+            app.keymap.key(Action::Open),
+            app.keymap.key(Action::Down),
+            app.keymap.key(Action::Up),
+            app.keymap.key(Action::DeleteView),
+            app.keymap.key(Action::Back)
+        ),
+        Mode::SaveView => format!(" save as: {}_  (Enter save, Esc cancel) ", app.input),
+        Mode::Help { .. } => format!(
+            " help — {}/{} scroll, any other key closes ",
+            app.keymap.key(Action::Down),
+            app.keymap.key(Action::Up)
+        ),
     };
     f.render_widget(Paragraph::new(status), chunks[0]);
 
     let body = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(28), Constraint::Percentage(72)])
+        .constraints([
+            Constraint::Percentage(app.session.left_width),
+            Constraint::Min(0),
+        ])
         .split(chunks[1]);
+    app.panes.left = body[0];
+    app.panes.notes = body[1];
 
     render_left(f, app, body[0]);
     render_notes(f, app, body[1]);
 
-    let preview = app.selected_note().map(preview_lines).unwrap_or_default();
+    let preview_area = if app.show_context {
+        let split = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Percentage(app.session.source_width),
+            ])
+            .split(chunks[2]);
+        app.panes.source = Some(split[1]);
+        render_context(f, app, split[1], " source ");
+        split[0]
+    } else {
+        app.panes.source = None;
+        chunks[2]
+    };
+    app.panes.preview = preview_area;
+    let preview_width = preview_area.width.saturating_sub(2) as usize;
+    let preview = app
+        .selected_note()
+        .map(|n| preview_lines(&app.formatter, &app.theme, n, preview_width))
+        .unwrap_or_default();
     f.render_widget(
-        Paragraph::new(preview).block(Block::default().borders(Borders::ALL).title(" preview ")),
-        chunks[2],
+        Paragraph::new(preview)
+            .wrap(Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL).title(" preview ")),
+        preview_area,
     );
 
-    if let Mode::Detail { scroll } = app.mode {
-        if let Some(note) = app.selected_note().cloned() {
-            render_detail(f, &note, scroll);
+    if let Mode::Detail { scroll, source } = app.mode {
+        if source {
+            let area = centered_rect(72, 70, f.area());
+            f.render_widget(Clear, area);
+            render_context(f, app, area, &detail_title(app.selected_note()));
+        } else if let Some(note) = app.selected_note().cloned() {
+            let blame = app.detail_blame.as_ref();
+            render_detail(f, &app.formatter, &app.theme, &note, blame, scroll);
         }
     }
 
-    if matches!(app.mode, Mode::Help) {
-        render_help(f);
+    if let (Mode::Stats { scroll }, Some(stats)) = (&app.mode, &app.stats) {
+        render_stats(f, &app.theme, stats, *scroll);
     }
+
+    if let Mode::Compare { scroll } = app.mode {
+        render_compare(
+            f,
+            &app.theme,
+            &app.compare,
+            &app.store.define(&app.compare),
+            scroll,
+        );
+    }
+
+    if let (Mode::Progress, Some(snapshots)) = (&app.mode, &app.snapshots) {
+        render_progress(f, &app.theme, snapshots);
+    }
+
+    if matches!(app.mode, Mode::Palette) {
+        render_palette(f, app);
+    }
+
+    if let (Mode::Views, Some(views)) = (&app.mode, &app.views) {
+        render_views(f, &app.theme, views, app.views_selected);
+    }
+
+    if let Mode::Edit(action) = app.mode {
+        if let Some(note) = app.selected_note() {
+            render_edit(f, &app.theme, action, note, &app.input);
+        }
+    }
+
+    if let Mode::Help { scroll } = &mut app.mode {
+        *scroll = render_help(f, &app.theme, &app.keymap, *scroll);
+    }
+}
+
+/// Subsequence match of `query` in `text`, ignoring case: lower is better
+/// (a late start and gaps between matched characters both cost), `None` if
+/// some character is missing.
+fn fuzzy_score(query: &str, text: &str) -> Option<usize> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    for (i, q) in query
+        .to_lowercase()
+        .chars()
+        .filter(|c| *c != ' ')
+        .enumerate()
+    {
+        let found = next + text[next..].iter().position(|c| *c == q)?;
+        score += if i == 0 { found } else { found - next };
+        next = found + 1;
+    }
+    Some(score)
+}
+
+fn render_views(f: &mut Frame, theme: &Theme, views: &BTreeMap<String, View>, selected: usize) {
+    let area = centered_rect(60, 50, f.area());
+    f.render_widget(Clear, area);
+    let width = views.keys().map(|n| n.chars().count()).max().unwrap_or(0);
+    let items: Vec<ListItem> = views
+        .iter()
+        .map(|(name, view)| {
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("{name:<width$}  ")),
+                Span::styled(view.describe(), theme.tag.tui()),
+            ]))
+        })
+        .collect();
+    let mut state = ListState::default().with_selected(Some(selected));
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" saved views "),
+        )
+        .highlight_style(theme.selection.tui());
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn render_palette(f: &mut Frame, app: &App) {
+    let area = centered_rect(60, 60, f.area());
+    f.render_widget(Clear, area);
+    let block = Block::default().borders(Borders::ALL).title(" run action ");
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let [prompt, rows] = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(0)])
+        .areas(inner);
+
+    f.render_widget(Paragraph::new(format!(":{}_", app.palette)), prompt);
+    let matches = app.palette_matches();
+    let width = matches.iter().map(|a| a.name().len()).max().unwrap_or(0);
+    let items: Vec<ListItem> = matches
+        .iter()
+        .map(|a| {
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("{:<width$}  ", a.name())),
+                Span::styled(
+                    format!("{:<8}", app.keymap.key(*a)),
+                    app.theme.location.tui(),
+                ),
+                Span::raw(a.description()),
+            ]))
+        })
+        .collect();
+    let mut state = ListState::default().with_selected(Some(app.palette_selected));
+    let list = List::new(items).highlight_style(app.theme.selection.tui());
+    f.render_stateful_widget(list, rows, &mut state);
+}
+
+/// `part` of `whole` columns as a pane width, kept within the limits.
+fn pane_percent(part: u16, whole: u16) -> u16 {
+    let percent = u32::from(part) * 100 / u32::from(whole.max(1));
+    (percent as u16).clamp(PANE_MIN, PANE_MAX)
+}
+
+fn contains(area: Rect, x: u16, y: u16) -> bool {
+    area.contains(ratatui::layout::Position { x, y })
+}
+
+/// Item index under (`x`, `y`) in a bordered list scrolled to `offset`.
+fn list_row(area: Rect, offset: usize, x: u16, y: u16) -> Option<usize> {
+    let inner = area.inner(ratatui::layout::Margin::new(1, 1));
+    contains(inner, x, y).then(|| offset + (y - inner.y) as usize)
 }
 
 fn render_left(f: &mut Frame, app: &mut App, area: Rect) {
@@ -576,17 +2280,63 @@ fn render_left(f: &mut Frame, app: &mut App, area: Rect) {
             Catalog::Tags => {
                 let mark = if app.selected.contains(t) {
                     "[x] "
+                } else if app.excluded.contains(t) {
+                    "[-] "
                 } else {
                     "[ ] "
                 };
-                ListItem::new(Line::from(format!("{mark}{t}")))
+                let count = app.tag_counts.get(t).copied().unwrap_or(0);
+                let line = Line::from(format!("{mark}{t}  {count}"));
+                // Picking it would empty the notes pane.
+                if count == 0 {
+                    ListItem::new(line.style(app.theme.muted.tui()))
+                } else {
+                    ListItem::new(line)
+                }
+            }
+            Catalog::Glossary => {
+                let definitions = app.store.define(t);
+                let badge = match definitions.len() {
+                    0 | 1 => String::new(),
+                    n => format!(" ×{n}"),
+                };
+                // Whatever room the term and badge leave, inside the border.
+                let room = (area.width as usize)
+                    .saturating_sub(2 + t.chars().count() + badge.chars().count() + 2);
+                let gloss = match definitions.first() {
+                    Some(note) if room > 1 => {
+                        format!("  {}", truncate(&plain_body(&note.text), room))
+                    }
+                    _ => String::new(),
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw(t.to_string()),
+                    Span::styled(badge, app.theme.badge.tui()),
+                    Span::styled(gloss, app.theme.location.tui()),
+                ]))
+            }
+            Catalog::Files => {
+                let (depth, name) = tree_label(t);
+                let notes = app.files.as_ref().and_then(|f| f.notes.get(t));
+                let style = if t.ends_with('/') {
+                    app.theme.heading.tui()
+                } else {
+                    app.theme.location.tui()
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw("  ".repeat(depth)),
+                    Span::styled(name.to_string(), style),
+                    Span::raw(format!("  {}", notes.copied().unwrap_or(0))),
+                ]))
             }
-            Catalog::Glossary => ListItem::new(Line::from(t.to_string())),
         })
         .collect();
 
     let focused = app.focus == Focus::Left && matches!(app.mode, Mode::Browse);
-    let label = app.left_label();
+    let label = match app.catalog {
+        Catalog::Tags if app.session.tags_by_count => "tags by count",
+        _ => app.left_label(),
+    };
     let title = if app.override_state.is_some() {
         format!(" {label} (override) ")
     } else if focused {
@@ -597,37 +2347,74 @@ fn render_left(f: &mut Frame, app: &mut App, area: Rect) {
 
     let block = Block::default().borders(Borders::ALL).title(title);
     let block = if focused {
-        block.border_style(Style::default().add_modifier(Modifier::BOLD))
+        block.border_style(app.theme.focus.tui())
     } else {
         block
     };
 
     let list = List::new(items)
         .block(block)
-        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        .highlight_style(app.theme.selection.tui());
 
     f.render_stateful_widget(list, area, &mut app.left_state);
 }
 
 fn render_notes(f: &mut Frame, app: &mut App, area: Rect) {
-    let items: Vec<ListItem> = app
-        .current_notes()
-        .into_iter()
-        .map(|n| {
+    let rows = &app.rows;
+    // Where the selection is among notes; file headers (`z`) don't count.
+    let is_note = |row: &Row| matches!(row, Row::Note(_));
+    let total = rows.iter().filter(|r| is_note(r)).count();
+    let position = app
+        .note_state
+        .selected()
+        .filter(|&i| rows.get(i).is_some_and(is_note))
+        .map(|i| rows[..=i].iter().filter(|r| is_note(r)).count());
+    let items: Vec<ListItem> = rows
+        .iter()
+        .map(|row| {
+            let (id, n) = match row {
+                Row::Note(id) => (*id, &app.store.notes()[*id]),
+                Row::File { title, notes } => {
+                    let fold = if app.collapsed.contains(title) {
+                        "▸"
+                    } else {
+                        "▾"
+                    };
+                    return ListItem::new(Line::from(vec![
+                        Span::styled(format!("{fold} {title}"), app.theme.location.tui()),
+                        Span::raw(format!("  ({notes})")),
+                    ]));
+                }
+            };
+            let indent = if app.group_files { "  " } else { "" };
+            let status = match n.status.label() {
+                Some(label) => format!("({label}) "),
+                None => String::new(),
+            };
             let kind = match &n.kind {
-                Kind::Fixme => "FIXME ".to_string(),
-                Kind::Todo => "TODO ".to_string(),
+                Kind::Fixme => Span::styled(format!("FIXME {status}"), app.theme.fixme.tui()),
+                Kind::Todo => Span::styled(format!("TODO {status}"), app.theme.todo.tui()),
                 Kind::Define { term } => {
                     if app.catalog == Catalog::Glossary && app.override_state.is_none() {
-                        String::new()
+                        Span::raw("")
                     } else {
-                        format!("def:{term} ")
+                        Span::styled(format!("def:{term} "), app.theme.term.tui())
                     }
                 }
-                Kind::Note => String::new(),
+                Kind::Note => Span::raw(""),
             };
             let summary = truncate(&plain_body(&n.text), 72);
-            ListItem::new(Line::from(vec![Span::raw(kind), Span::raw(summary)]))
+            let summary = if n.is_open() {
+                Span::raw(summary)
+            } else {
+                Span::styled(summary, app.theme.done.tui())
+            };
+            let mark = match (app.marked.contains(&id), app.marked.is_empty()) {
+                (true, _) => Span::styled("● ", app.theme.tag.tui()),
+                (false, false) => Span::raw("  "),
+                (false, true) => Span::raw(""),
+            };
+            ListItem::new(Line::from(vec![Span::raw(indent), mark, kind, summary]))
         })
         .collect();
 
@@ -648,17 +2435,13 @@ fn render_notes(f: &mut Frame, app: &mut App, area: Rect) {
         None => {
             let label = match app.catalog {
                 Catalog::Tags => {
-                    if app.selected.is_empty() {
-                        "all".to_string()
-                    } else {
-                        app.selected
-                            .iter()
-                            .map(|t| format!("#{t}"))
-                            .collect::<Vec<_>>()
-                            .join(" ")
+                    let (tags, exclude) = app.tag_selection();
+                    match view::tag_expression(&tags, app.any_tag, &exclude) {
+                        e if e.is_empty() => "all".to_string(),
+                        e => e,
                     }
                 }
-                Catalog::Glossary => app.selected_left().unwrap_or_default(),
+                Catalog::Glossary | Catalog::Files => app.selected_left().unwrap_or_default(),
             };
             match (label.is_empty(), focused) {
                 (false, true) => format!(" notes ({label}) * "),
@@ -668,138 +2451,495 @@ fn render_notes(f: &mut Frame, app: &mut App, area: Rect) {
             }
         }
     };
+    let title = match (position, total) {
+        (_, 0) => title,
+        (Some(n), total) => format!("{title}{n}/{total} "),
+        (None, total) => format!("{title}-/{total} "),
+    };
+    let title = match app.marked.len() {
+        0 => title,
+        n => format!("{title}· {n} marked "),
+    };
 
     let block = Block::default().borders(Borders::ALL).title(title);
     let block = if focused {
-        block.border_style(Style::default().add_modifier(Modifier::BOLD))
+        block.border_style(app.theme.focus.tui())
     } else {
         block
     };
 
     let list = List::new(items)
         .block(block)
-        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        .highlight_style(app.theme.selection.tui());
 
     f.render_stateful_widget(list, area, &mut app.note_state);
 }
 
-fn render_detail(f: &mut Frame, note: &Note, scroll: u16) {
+/// Definitions of `term` in columns. Words the first definition lacks are
+/// marked added in the others, and its own words they lack removed.
+fn render_compare(f: &mut Frame, theme: &Theme, term: &str, notes: &[&Note], scroll: u16) {
+    let area = centered_rect(90, 70, f.area());
+    f.render_widget(Clear, area);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" {term} — {} definitions ", notes.len()));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let words = |note: &Note| -> Vec<String> {
+        plain_body(&note.text)
+            .split_whitespace()
+            .map(str::to_owned)
+            .collect()
+    };
+    let key = |word: &str| {
+        word.trim_matches(|c: char| !c.is_alphanumeric())
+            .to_lowercase()
+    };
+    let texts: Vec<Vec<String>> = notes.iter().map(|n| words(n)).collect();
+    let first: HashSet<String> = texts
+        .first()
+        .into_iter()
+        .flatten()
+        .map(|w| key(w))
+        .collect();
+    let others: HashSet<String> = texts.iter().skip(1).flatten().map(|w| key(w)).collect();
+
+    let columns = Layout::horizontal(vec![Constraint::Ratio(1, notes.len() as u32); notes.len()])
+        .split(inner);
+    for (i, (note, text)) in notes.iter().zip(&texts).enumerate() {
+        let spans: Vec<Span> = text
+            .iter()
+            .flat_map(|word| {
+                let style = match i {
+                    0 if !others.contains(&key(word)) => theme.removed.tui(),
+                    1.. if !first.contains(&key(word)) => theme.added.tui(),
+                    _ => Style::default(),
+                };
+                [Span::styled(word.clone(), style), Span::raw(" ")]
+            })
+            .collect();
+        let title = format!(" {}:{} ", note.path.display(), note.line);
+        let paragraph = Paragraph::new(Line::from(spans))
+            .wrap(Wrap { trim: true })
+            .scroll((scroll, 0))
+            .block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(paragraph, columns[i]);
+    }
+}
+
+fn render_detail(
+    f: &mut Frame,
+    formatter: &Formatter,
+    theme: &Theme,
+    note: &Note,
+    blame: Option<&Blame>,
+    scroll: u16,
+) {
     let area = centered_rect(72, 70, f.area());
     f.render_widget(Clear, area);
 
-    let title = match &note.kind {
+    let title = detail_title(Some(note));
+    let width = area.width.saturating_sub(2) as usize;
+    let lines = detail_lines(formatter, theme, note, blame, width);
+    let paragraph = Paragraph::new(Text::from(lines))
+        .scroll((scroll, 0))
+        .block(Block::default().borders(Borders::ALL).title(title));
+
+    f.render_widget(paragraph, area);
+}
+
+fn detail_title(note: Option<&Note>) -> String {
+    let Some(note) = note else {
+        return String::new();
+    };
+    let status = match note.status.label() {
+        Some(label) => format!("({label}) "),
+        None => String::new(),
+    };
+    match &note.kind {
         Kind::Define { term } => format!(" {term} "),
-        Kind::Fixme => " FIXME ".to_string(),
-        Kind::Todo => " TODO ".to_string(),
+        Kind::Fixme => format!(" FIXME {status}"),
+        Kind::Todo => format!(" TODO {status}"),
         Kind::Note => " note ".to_string(),
+    }
+}
+
+/// The selected note's source file, scrolled to the comment.
+fn render_context(f: &mut Frame, app: &mut App, area: Rect, title: &str) {
+    let height = area.height.saturating_sub(2) as usize;
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(title.to_owned());
+    let Some(note) = app.selected_note().cloned() else {
+        f.render_widget(block, area);
+        return;
+    };
+    app.ensure_context();
+    let theme = &app.theme;
+    let lines = match &app.context {
+        Some((_, context)) => {
+            app.context_scroll = context.clamp_scroll(height, app.context_scroll);
+            let first = context.first_line(height, app.context_scroll);
+            context_lines(theme, &note, context, first, height)
+        }
+        None => vec![Line::styled(
+            format!("{} is not readable", note.path.display()),
+            theme.location.tui(),
+        )],
     };
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
 
-    let paragraph = Paragraph::new(Text::from(detail_lines(note)))
-        .wrap(Wrap { trim: false })
+/// `height` source lines from index `first`, numbered, with the comment
+/// highlighted.
+fn context_lines(
+    theme: &Theme,
+    note: &Note,
+    context: &SourceContext,
+    first: usize,
+    height: usize,
+) -> Vec<Line<'static>> {
+    let width = context.lines.len().to_string().len();
+    context
+        .lines
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(height)
+        .map(|(i, text)| {
+            let number = i + 1;
+            let gutter = format!("{number:>width$} ");
+            if context.comment.contains(&number) {
+                let mut spans = vec![Span::styled(gutter, theme.selection.tui())];
+                spans.extend(comment_spans(theme, note, text));
+                Line::from(spans)
+            } else {
+                Line::from(vec![
+                    Span::styled(gutter, theme.location.tui()),
+                    Span::raw(text.clone()),
+                ])
+            }
+        })
+        .collect()
+}
+
+/// A comment line split into words: delimiters dimmed, the keyword in the
+/// note's kind style, and `#tags` in tag style.
+fn comment_spans(theme: &Theme, note: &Note, text: &str) -> Vec<Span<'static>> {
+    let keyword = match note.kind {
+        Kind::Fixme => theme.fixme.tui(),
+        Kind::Todo => theme.todo.tui(),
+        Kind::Define { .. } => theme.term.tui(),
+        Kind::Note => theme.heading.tui(),
+    };
+    text.split_inclusive(' ')
+        .map(|word| {
+            let bare = word.trim_end().trim_end_matches(':');
+            let style = if matches!(bare, "<!--" | "-->") {
+                theme.location.tui()
+            } else if matches!(
+                bare.to_ascii_uppercase().as_str(),
+                "FIXME" | "TODO" | "NOTE" | "DONE" | "WONTFIX" | "DEFINE"
+            ) {
+                keyword
+            } else if bare.starts_with('#') && bare.len() > 1 {
+                theme.tag.tui()
+            } else {
+                Style::default()
+            };
+            Span::styled(word.to_owned(), style)
+        })
+        .collect()
+}
+
+fn render_stats(f: &mut Frame, theme: &Theme, stats: &Stats, scroll: u16) {
+    let area = centered_rect(80, 80, f.area());
+    f.render_widget(Clear, area);
+    let paragraph = Paragraph::new(stats_lines(theme, stats))
         .scroll((scroll, 0))
-        .block(Block::default().borders(Borders::ALL).title(title));
+        .block(Block::default().borders(Borders::ALL).title(" stats "));
+    f.render_widget(paragraph, area);
+}
 
+/// The `notes stats` text report as styled lines.
+fn stats_lines(theme: &Theme, stats: &Stats) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    for (i, section) in stats.sections().into_iter().enumerate() {
+        if i > 0 {
+            lines.push(Line::from(""));
+        }
+        lines.push(Line::styled(
+            format!(" {}", section.title),
+            theme.heading.tui(),
+        ));
+        let width = section.rows.iter().map(|(l, _)| l.chars().count()).max();
+        for (label, value) in section.rows {
+            lines.push(Line::from(format!(
+                "   {label:<width$}  {value}",
+                width = width.unwrap_or(0)
+            )));
+        }
+    }
+    lines
+}
+
+fn render_edit(f: &mut Frame, theme: &Theme, action: EditAction, note: &Note, input: &str) {
+    let area = centered_rect(70, 40, f.area());
+    f.render_widget(Clear, area);
+    let title = match action {
+        EditAction::AddTag => " add tag ",
+        EditAction::RemoveTag => " remove tag ",
+        EditAction::Text => " edit note text ",
+    };
+    let mut lines = vec![location_line(theme, note), Line::from("")];
+    if action == EditAction::RemoveTag {
+        let tags: Vec<String> = note.tags.iter().map(|t| format!("#{t}")).collect();
+        lines.push(Line::styled(tags.join(" "), theme.tag.tui()));
+        lines.push(Line::from(""));
+    }
+    lines.push(Line::from(format!("{input}_")));
+    let paragraph = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title(title));
     f.render_widget(paragraph, area);
 }
 
-fn render_help(f: &mut Frame) {
-    let area = centered_rect(74, 80, f.area());
+/// One sparkline per series from `notes snapshot`, oldest on the left.
+fn render_progress(f: &mut Frame, theme: &Theme, snapshots: &[Snapshot]) {
+    let area = centered_rect(80, 70, f.area());
+    f.render_widget(Clear, area);
+    let report = progress::report(snapshots);
+    let block = Block::default().borders(Borders::ALL).title(" progress ");
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Ratio(1, 3),
+            Constraint::Ratio(1, 3),
+            Constraint::Ratio(1, 3),
+        ])
+        .split(inner);
+    f.render_widget(
+        Paragraph::new(format!(" {}", report[0])).style(theme.heading.tui()),
+        rows[0],
+    );
+    if snapshots.is_empty() {
+        return;
+    }
+    let styles = [theme.fixme.tui(), theme.todo.tui(), theme.tag.tui()];
+    for ((series, style), area) in progress::series(snapshots)
+        .into_iter()
+        .zip(styles)
+        .zip(&rows[1..])
+    {
+        let data: Vec<u64> = series.values.iter().map(|&v| v as u64).collect();
+        let title = format!(" {} {} ", series.label, series.change());
+        f.render_widget(
+            Sparkline::default()
+                .block(Block::default().borders(Borders::TOP).title(title))
+                .data(&data)
+                .style(style),
+            *area,
+        );
+    }
+}
+
+/// Help for the active keymap, scrolled by `scroll`; returns the scroll
+/// clamped so the last line stays in view.
+fn render_help(f: &mut Frame, theme: &Theme, keymap: &Keymap, scroll: u16) -> u16 {
+    let area = centered_rect(74, 90, f.area());
     f.render_widget(Clear, area);
 
-    let heading_style = Style::new().add_modifier(Modifier::BOLD);
-    let mut lines: Vec<Line<'static>> = Vec::new();
+    let lines = help_lines(theme, keymap);
+    let height = area.height.saturating_sub(2) as usize;
+    let scroll = scroll.min(lines.len().saturating_sub(height) as u16);
+    let paragraph = Paragraph::new(lines).scroll((scroll, 0)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" help (any key closes) "),
+    );
+    f.render_widget(paragraph, area);
+    scroll
+}
 
-    for (heading, rows) in [
+fn help_lines(theme: &Theme, keymap: &Keymap) -> Vec<Line<'static>> {
+    let sections: [(&str, &[Action]); 5] = [
         (
             "Navigation",
             &[
-                ("j / k  ↓ / ↑", "move the focused pane"),
-                ("tab", "switch focus: left ↔ notes"),
-                ("enter", "open detail  /  confirm"),
-                ("esc", "close overlay  /  cancel  /  back"),
-            ][..],
+                Action::Down,
+                Action::Up,
+                Action::Top,
+                Action::Bottom,
+                Action::PageDown,
+                Action::PageUp,
+                Action::HalfDown,
+                Action::HalfUp,
+                Action::Focus,
+                Action::Open,
+                Action::Back,
+                Action::Narrow,
+                Action::Widen,
+            ],
         ),
         (
             "Catalog",
             &[
-                ("g", "toggle tags ↔ glossary"),
-                ("/", "filter the left list"),
-                ("space", "toggle a tag pick (tags)"),
-            ][..],
+                Action::Glossary,
+                Action::Files,
+                Action::Compare,
+                Action::Filter,
+                Action::ToggleTag,
+                Action::ExcludeTag,
+                Action::TagMode,
+                Action::TagSort,
+                Action::Views,
+                Action::SaveView,
+                Action::DeleteView,
+            ],
         ),
         (
             "Lists",
             &[
-                ("e", "errata — show FIXMEs"),
-                ("p", "pending — show TODOs"),
-                ("f", "full-text search"),
-            ][..],
+                Action::Errata,
+                Action::Pending,
+                Action::Fts,
+                Action::Find,
+                Action::FindNext,
+                Action::FindPrev,
+                Action::Stats,
+                Action::Progress,
+                Action::Group,
+                Action::Source,
+                Action::SourceDown,
+                Action::SourceUp,
+            ],
         ),
         (
-            "Other",
+            "Edit",
             &[
-                ("y", "yank selected note to clipboard"),
-                ("h / ?", "this help"),
-                ("q", "quit"),
-            ][..],
+                Action::Yank,
+                Action::Mark,
+                Action::MarkAll,
+                Action::Export,
+                Action::ToggleStatus,
+                Action::AddTag,
+                Action::RemoveTag,
+                Action::EditText,
+                Action::Undo,
+            ],
         ),
-    ] {
-        lines.push(Line::from(format!(" {heading}")).style(heading_style));
-        for (key, desc) in rows {
+        ("Other", &[Action::Palette, Action::Help, Action::Quit]),
+    ];
+    let width = Action::ALL
+        .iter()
+        .map(|a| keymap.keys(*a).chars().count())
+        .max()
+        .unwrap_or(0)
+        + 2;
+
+    let mut lines = Vec::new();
+    for (heading, actions) in sections {
+        lines.push(Line::from(format!(" {heading}")).style(theme.heading.tui()));
+        for action in actions {
             lines.push(Line::from(vec![
                 Span::raw("   "),
-                Span::raw(format!("{key:<14}")),
-                Span::raw(*desc),
+                Span::raw(format!("{:<width$}", keymap.keys(*action))),
+                Span::raw(action.description()),
             ]));
         }
     }
+    lines
+}
 
-    let paragraph = Paragraph::new(lines).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .title(" help (any key closes) "),
-    );
-    f.render_widget(paragraph, area);
+fn detail_lines(
+    formatter: &Formatter,
+    theme: &Theme,
+    note: &Note,
+    blame: Option<&Blame>,
+    width: usize,
+) -> Vec<Line<'static>> {
+    let mut lines = vec![location_line(theme, note)];
+    if let Some(blame) = blame {
+        lines.push(Line::styled(blame_text(blame), theme.location.tui()));
+    }
+    lines.push(Line::from(""));
+    lines.extend(body_lines(formatter, theme, note, width));
+    lines
 }
 
-fn detail_lines(note: &Note) -> Vec<Line<'static>> {
-    let loc = format!("{}:{}", note.path.display(), note.line);
-    vec![
-        Line::from(loc),
-        Line::from(""),
-        styled_body_line(&note.text),
-    ]
+fn preview_lines(
+    formatter: &Formatter,
+    theme: &Theme,
+    note: &Note,
+    width: usize,
+) -> Vec<Line<'static>> {
+    let mut lines = vec![location_line(theme, note)];
+    lines.extend(body_lines(formatter, theme, note, width));
+    lines
 }
 
-fn preview_lines(note: &Note) -> Vec<Line<'static>> {
+/// "author · age · commit" for the detail overlay.
+fn blame_text(blame: &Blame) -> String {
+    if !blame.is_committed() {
+        return "not committed yet".into();
+    }
+    format!(
+        "{} · {} · {}",
+        blame.author,
+        date::age(blame.time, date::now()),
+        blame.short_commit()
+    )
+}
+
+fn location_line(theme: &Theme, note: &Note) -> Line<'static> {
     let loc = format!("{}:{}", note.path.display(), note.line);
-    vec![Line::from(loc), styled_body_line(&note.text)]
+    Line::styled(loc, theme.location.tui())
+}
+
+/// Body wrapped by the CLI's hyphenating wrapper, so breaks match `notes all`.
+fn body_lines(
+    formatter: &Formatter,
+    theme: &Theme,
+    note: &Note,
+    width: usize,
+) -> Vec<Line<'static>> {
+    formatter
+        .wrap_body(note, width)
+        .into_iter()
+        .map(|words| styled_line(theme, words))
+        .collect()
 }
 
-fn styled_body_line(text: &str) -> Line<'static> {
+fn styled_line(theme: &Theme, words: Vec<BodyWord>) -> Line<'static> {
     let mut spans = Vec::new();
-    for (i, word) in styled_words(text).into_iter().enumerate() {
+    for (i, word) in words.into_iter().enumerate() {
         if i > 0 {
             spans.push(Span::raw(" "));
         }
         for (seg_text, seg_style) in word.segments {
-            spans.push(Span::styled(seg_text, tui_style(seg_style)));
+            spans.push(Span::styled(seg_text, tui_style(theme, seg_style)));
         }
     }
     Line::from(spans)
 }
 
-fn tui_style(style: BodyStyle) -> Style {
-    let mut s = Style::default();
+fn tui_style(theme: &Theme, style: BodyStyle) -> Style {
+    let mut s = if style.tag {
+        theme.tag.tui()
+    } else {
+        Style::default()
+    };
     if style.bold {
         s = s.add_modifier(Modifier::BOLD);
     }
     if style.italic {
         s = s.add_modifier(Modifier::ITALIC);
     }
-    if style.tag {
-        s = s.add_modifier(Modifier::UNDERLINED);
-    }
     s
 }
 
@@ -810,6 +2950,40 @@ fn normalize_tag_filter(s: &str) -> String {
         .to_ascii_lowercase()
 }
 
+/// Status-bar text for a write-back, e.g. `+#army -#plot` or `marked done`.
+fn edit_summary(old: &Note, new: &Note) -> String {
+    if old.status != new.status {
+        return format!("marked {}", new.status.label().unwrap_or("open"));
+    }
+    let has = |note: &Note, tag: &str| note.tags.iter().any(|t| t.eq_ignore_ascii_case(tag));
+    let mut changes: Vec<String> = new
+        .tags
+        .iter()
+        .filter(|t| !has(old, t))
+        .map(|t| format!("+#{t}"))
+        .collect();
+    changes.extend(
+        old.tags
+            .iter()
+            .filter(|t| !has(new, t))
+            .map(|t| format!("-#{t}")),
+    );
+    if old.text != new.text && changes.is_empty() {
+        changes.push("text edited".into());
+    }
+    changes.join(" ")
+}
+
+/// Keep a list selection inside `len` items after the list changed.
+fn clamp(state: &mut ListState, len: usize) {
+    match state.selected() {
+        _ if len == 0 => state.select(None),
+        Some(i) if i >= len => state.select(Some(len - 1)),
+        None => state.select(Some(0)),
+        _ => {}
+    }
+}
+
 fn yank_text(note: &Note) -> String {
     match &note.kind {
         Kind::Define { term } => format!("{term}\n{}", plain_body(&note.text)),
@@ -850,12 +3024,15 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
 mod tests {
     use super::*;
     use ratatui::backend::TestBackend;
+    use std::path::Path;
 
-    #[test]
-    fn help_renders_all_sections() {
-        let backend = TestBackend::new(82, 26);
+    fn help_screen(keymap: &Keymap, scroll: u16) -> (String, u16) {
+        let backend = TestBackend::new(82, 36);
         let mut terminal = Terminal::new(backend).unwrap();
-        terminal.draw(render_help).unwrap();
+        let mut clamped = 0;
+        terminal
+            .draw(|f| clamped = render_help(f, &Theme::default(), keymap, scroll))
+            .unwrap();
 
         let buf = terminal.backend().buffer();
         let area = buf.area();
@@ -865,6 +3042,16 @@ mod tests {
             .flat_map(|y| content[y * w..(y + 1) * w].iter())
             .map(|c| c.symbol().chars().next().unwrap_or(' '))
             .collect();
+        (rendered, clamped)
+    }
+
+    #[test]
+    fn help_renders_all_sections() {
+        let keymap = Keymap::default();
+        let (top, _) = help_screen(&keymap, 0);
+        let (bottom, scroll) = help_screen(&keymap, u16::MAX);
+        assert!(scroll > 0 && scroll < 40);
+        let rendered = top + &bottom;
 
         for needle in [
             "Navigation", "Catalog", "Lists", "Other", "quit", "h / ?", "pending",
@@ -872,4 +3059,661 @@ mod tests {
             assert!(rendered.contains(needle), "help dialog missing {needle:?}");
         }
     }
+
+    #[test]
+    fn detail_body_uses_hyphenating_wrap() {
+        let note = Note {
+            path: "t.md".into(),
+            line: 3,
+            kind: Kind::Note,
+            status: Status::Open,
+            tags: vec![],
+            text: "a wordy antidisestablishmentarianism ends here".into(),
+        };
+        let lines = detail_lines(&Formatter::default(), &Theme::default(), &note, None, 14);
+        let body: Vec<String> = lines.iter().skip(2).map(|l| l.to_string()).collect();
+        assert!(body.iter().all(|l| l.chars().count() <= 14), "{body:?}");
+        assert!(body.iter().any(|l| l.ends_with('-')), "{body:?}");
+    }
+
+    #[test]
+    fn stats_panel_matches_cli_sections() {
+        let notes = crate::note::Parser::new().parse_file(
+            std::path::Path::new("ch01.md"),
+            "<!-- FIXME dates #plot -->\n<!-- NOTE def tithe a tenth. -->\n",
+        );
+        let stats = Stats::collect(&NoteStore::from_notes(notes), 10);
+        let text: Vec<String> = stats_lines(&Theme::default(), &stats)
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+        for needle in ["Notes", "Glossary", "Top tags", "#plot", "ch01.md"] {
+            assert!(
+                text.iter().any(|l| l.contains(needle)),
+                "missing {needle:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn detail_shows_author_and_age() {
+        let note = Note {
+            path: "t.md".into(),
+            line: 3,
+            kind: Kind::Note,
+            status: Status::Open,
+            tags: vec![],
+            text: "body".into(),
+        };
+        let blame = Blame {
+            commit: "4f32940aa4f32940aa4f32940aa4f32940aa4f32".into(),
+            author: "Ada Lovelace".into(),
+            author_mail: "ada@example.com".into(),
+            time: date::now() - 2 * 86_400,
+        };
+        let formatter = Formatter::default();
+        let lines = detail_lines(&formatter, &Theme::default(), &note, Some(&blame), 40);
+        assert_eq!(lines[1].to_string(), "Ada Lovelace · 2 days ago · 4f32940a");
+        assert_eq!(lines[3].to_string(), "body");
+    }
+
+    #[test]
+    fn progress_chart_titles_each_series() {
+        let snapshots: Vec<Snapshot> = [(12, 5), (6, 5), (2, 3)]
+            .into_iter()
+            .enumerate()
+            .map(|(day, (errata, pending))| Snapshot {
+                time: 1_760_000_000 + day as i64 * 86_400,
+                errata,
+                pending,
+                tags: 4,
+            })
+            .collect();
+        let mut terminal = Terminal::new(TestBackend::new(80, 30)).unwrap();
+        terminal
+            .draw(|f| render_progress(f, &Theme::default(), &snapshots))
+            .unwrap();
+
+        let buf = terminal.backend().buffer();
+        let width = buf.area().width as usize;
+        let rows: Vec<String> = buf
+            .content()
+            .chunks(width)
+            .map(|row| row.iter().map(|c| c.symbol()).collect())
+            .collect();
+        for needle in [
+            "3 snapshots",
+            "FIXME 12 → 2 (-10)",
+            "TODO 5 → 3 (-2)",
+            "tags 4 → 4 (+0)",
+        ] {
+            assert!(
+                rows.iter().any(|r| r.contains(needle)),
+                "missing {needle:?}"
+            );
+        }
+        assert!(rows.iter().any(|r| r.contains('█')));
+    }
+
+    #[test]
+    fn tag_edits_write_back_reindex_and_undo() {
+        let source = "<!-- NOTE the knives march at dawn #army -->\n";
+        let (file, notes) = writeback::scratch("tui-edits.md", source);
+        let path = &file.0;
+        let mut app = App::new(
+            NoteStore::from_notes(notes),
+            Analyzer::default(),
+            Formatter::default(),
+            Theme::default(),
+        );
+        app.focus = Focus::Notes;
+
+        app.open_edit(EditAction::AddTag);
+        app.input = "#cavalry".into();
+        app.submit_edit(EditAction::AddTag);
+        assert_eq!(app.status, format!("{}:1 +#cavalry", path.display()));
+        assert!(app.tags.contains(&"cavalry".to_string()));
+        assert_eq!(app.fts.query_ids(&app.store, "tag:cavalry").unwrap(), [0]);
+        assert!(std::fs::read_to_string(path)
+            .unwrap()
+            .contains("#army #cavalry -->"));
+
+        app.open_edit(EditAction::Text);
+        app.input = "the knives ride --> at noon".into();
+        app.submit_edit(EditAction::Text);
+        assert_eq!(app.status, "notes cannot contain <!-- or -->");
+        assert_eq!(app.undo.len(), 1);
+
+        app.open_edit(EditAction::Text);
+        app.input = "the knives ride at noon #cavalry".into();
+        app.submit_edit(EditAction::Text);
+        assert_eq!(app.fts.query_ids(&app.store, "noon").unwrap(), [0]);
+        assert!(!app.tags.contains(&"army".to_string()));
+
+        app.undo_edit();
+        app.undo_edit();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), source);
+        assert!(app.store.tag_ids("cavalry").is_empty());
+        app.undo_edit();
+        assert_eq!(app.status, "nothing to undo");
+    }
+
+    #[test]
+    fn file_tree_lists_folders_before_their_files() {
+        let parser = crate::note::Parser::new();
+        let mut notes = parser.parse_file(
+            Path::new("part1/ch01.md"),
+            "<!-- NOTE a -->\n<!-- TODO b -->\n",
+        );
+        notes.extend(parser.parse_file(Path::new("./intro.md"), "<!-- NOTE c -->\n"));
+        let mut store = NoteStore::from_notes(notes);
+        store.add_file_for_test("part1/ch01.md");
+        store.add_file_for_test("part1/ch02.md");
+        store.add_file_for_test("./intro.md");
+
+        let tree = FileTree::build(&store);
+        assert_eq!(
+            tree.keys,
+            ["intro.md", "part1/", "part1/ch01.md", "part1/ch02.md"]
+        );
+        assert_eq!(tree.notes["part1/"], 2);
+        assert_eq!(tree.notes["part1/ch01.md"], 2);
+        assert_eq!(tree.notes["intro.md"], 1);
+        assert_eq!(tree.notes.get("part1/ch02.md"), None);
+        assert_eq!(tree_label("part1/ch01.md"), (1, "ch01.md"));
+        assert_eq!(tree_label("part1/"), (0, "part1/"));
+        let parts: Vec<OsString> = ["part1", "sub", "ch03.md"].map(OsString::from).into();
+        assert_eq!(tree_key(&parts, false), "part1/sub/ch03.md");
+        assert_eq!(tree_key(&parts[..2], true), "part1/sub/");
+    }
+
+    #[test]
+    fn grouped_rows_fold_under_file_headers() {
+        let parser = crate::note::Parser::new();
+        let mut notes =
+            parser.parse_file(Path::new("ch01.md"), "<!-- NOTE a -->\n<!-- NOTE b -->\n");
+        notes.extend(parser.parse_file(Path::new("ch02.md"), "<!-- NOTE c -->\n"));
+        let mut app = App::new(
+            NoteStore::from_notes(notes),
+            Analyzer::default(),
+            Formatter::default(),
+            Theme::default(),
+        );
+        app.focus = Focus::Notes;
+        app.select_next_note();
+        assert_eq!(app.selected_note().unwrap().text, "b");
+
+        app.toggle_grouping();
+        assert_eq!(app.rows().len(), 5);
+        assert_eq!(app.note_state.selected(), Some(2));
+        assert_eq!(app.selected_note().unwrap().text, "b");
+        // Headers are not counted in the title's position.
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|c| c.symbol())
+            .collect();
+        assert!(screen.contains(" 2/3 "));
+
+        app.note_state.select(Some(0));
+        app.activate();
+        let rows = app.rows();
+        assert_eq!(rows.len(), 3);
+        assert!(matches!(&rows[0], Row::File { title, notes: 2 } if title == "ch01.md"));
+    }
+
+    #[test]
+    fn source_context_numbers_lines_and_highlights_the_comment() {
+        let source = "intro\n<!-- FIXME the bridge #army -->\nafter\n";
+        let note = crate::note::Parser::new().parse_file(Path::new("ch01.md"), source)[0].clone();
+        let context = SourceContext {
+            lines: source.lines().map(str::to_owned).collect(),
+            comment: 2..=2,
+        };
+        let theme = Theme::default();
+        let lines = context_lines(&theme, &note, &context, 1, 5);
+        assert_eq!(lines.len(), 2);
+        let text: String = lines[0].spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(text, "2 <!-- FIXME the bridge #army -->");
+        let styled = |word: &str| {
+            lines[0]
+                .spans
+                .iter()
+                .find(|s| s.content.trim() == word)
+                .unwrap()
+                .style
+        };
+        assert_eq!(styled("FIXME"), theme.fixme.tui());
+        assert_eq!(styled("#army"), theme.tag.tui());
+        assert_eq!(lines[1].spans[1].content, "after");
+    }
+
+    #[test]
+    fn palette_fuzzy_matches_and_runs_actions() {
+        assert_eq!(fuzzy_score("err", "errata"), Some(0));
+        assert_eq!(fuzzy_score("tt", "toggle tag"), Some(6));
+        assert_eq!(fuzzy_score("xyz", "toggle tag"), None);
+
+        let notes = crate::note::Parser::new()
+            .parse_file(Path::new("ch01.md"), "<!-- FIXME a -->\n<!-- NOTE b -->\n");
+        let mut app = App::new(
+            NoteStore::from_notes(notes),
+            Analyzer::default(),
+            Formatter::default(),
+            Theme::default(),
+        );
+        app.on_action(Action::Palette);
+        assert!(matches!(app.mode, Mode::Palette));
+        app.palette = "errata".into();
+        assert_eq!(app.palette_matches()[0], Action::Errata);
+        assert!(!app.palette_matches().contains(&Action::Palette));
+        assert!(!app.run_palette());
+        assert!(matches!(app.mode, Mode::Browse));
+        assert!(app.override_state.is_some());
+        assert_eq!(app.rows().len(), 1);
+
+        app.on_action(Action::Palette);
+        app.palette = "quit".into();
+        assert!(app.run_palette());
+    }
+
+    #[test]
+    fn views_capture_and_restore_the_selection() {
+        let parser = crate::note::Parser::new();
+        let mut notes = parser.parse_file(
+            Path::new("ch01.md"),
+            "<!-- NOTE a #army #navy -->\n<!-- NOTE b #army -->\n",
+        );
+        notes.extend(parser.parse_file(Path::new("ch02.md"), "<!-- TODO c -->\n"));
+        let mut store = NoteStore::from_notes(notes);
+        store.add_file_for_test("ch01.md");
+        store.add_file_for_test("ch02.md");
+        let mut app = App::new(
+            store,
+            Analyzer::default(),
+            Formatter::default(),
+            Theme::default(),
+        );
+        app.selected = ["army".to_string(), "navy".to_string()].into();
+        let tags = app.current_view();
+        assert_eq!(tags.describe(), "#army #navy");
+
+        app.show_files();
+        app.left_state.select(Some(1));
+        let files = app.current_view();
+        assert_eq!(files.key.as_deref(), Some("ch02.md"));
+        app.show_pending();
+        assert_eq!(app.current_view().list, Some(OverrideKind::Pending));
+
+        app.apply_view(&tags);
+        assert!(app.catalog == Catalog::Tags && app.override_state.is_none());
+        assert_eq!(app.rows().len(), 1);
+        app.apply_view(&files);
+        assert_eq!(app.selected_note().unwrap().text, "c");
+
+        // The views list follows the keymap.
+        app.keymap = Keymap::with_bindings(&[("down", &["ctrl-n"])]).unwrap();
+        app.views = Some([("files".into(), files), ("tags".into(), tags)].into());
+        app.mode = Mode::Views;
+        app.on_key("ctrl-n".parse().unwrap());
+        assert_eq!(app.views_selected, 1);
+        app.on_key("enter".parse().unwrap());
+        assert!(matches!(app.mode, Mode::Browse));
+        assert_eq!(app.status, "view tags");
+        assert_eq!(app.rows().len(), 1);
+    }
+
+    #[test]
+    fn marked_notes_export_together() {
+        let notes = crate::note::Parser::new().parse_file(
+            Path::new("ch01.md"),
+            "<!-- FIXME a -->\n<!-- NOTE b -->\n<!-- FIXME c -->\n",
+        );
+        let mut app = App::new(
+            NoteStore::from_notes(notes),
+            Analyzer::default(),
+            Formatter::default(),
+            Theme::default(),
+        );
+        app.on_action(Action::Errata);
+        app.on_action(Action::MarkAll);
+        assert_eq!(app.marked, [0, 2].into());
+        app.on_action(Action::MarkAll);
+        assert!(app.marked.is_empty());
+
+        app.on_action(Action::Mark);
+        assert_eq!(app.selected_note().unwrap().text, "c");
+        app.on_action(Action::Back);
+        app.show_tags();
+        app.focus = Focus::Notes;
+        app.on_action(Action::Mark);
+        app.on_action(Action::Mark);
+        assert_eq!(app.batch().len(), 2);
+
+        app.on_action(Action::Export);
+        app.export(ExportFormat::Markdown, "-");
+        assert_eq!(
+            app.stdout.as_deref(),
+            Some("- [ ] **FIXME** a — `ch01.md:1`\n- b — `ch01.md:2`\n")
+        );
+        let path = std::env::temp_dir().join(format!("notes-export-{}.json", std::process::id()));
+        app.export(ExportFormat::Json, path.to_str().unwrap());
+        assert_eq!(app.status, format!("wrote 2 notes to {}", path.display()));
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .contains("\"kind\": \"NOTE\""));
+        app.export(ExportFormat::Text, path.to_str().unwrap());
+        assert_eq!(
+            app.status,
+            format!("{} exists; export to a new file", path.display())
+        );
+        let kept = std::fs::read_to_string(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(kept.unwrap().starts_with('['));
+    }
+
+    #[test]
+    fn prompts_recall_history() {
+        let notes =
+            crate::note::Parser::new().parse_file(Path::new("ch01.md"), "<!-- NOTE a -->\n");
+        let mut app = App::new(
+            NoteStore::from_notes(notes),
+            Analyzer::default(),
+            Formatter::default(),
+            Theme::default(),
+        );
+        app.session.fts_history = vec!["army".into(), "navy".into()];
+        app.on_action(Action::Fts);
+        app.fts_query = "dra".into();
+        app.recall(true);
+        assert_eq!(app.fts_query, "navy");
+        app.recall(true);
+        app.recall(true);
+        assert_eq!(app.fts_query, "army");
+        app.recall(false);
+        app.recall(false);
+        assert_eq!(app.fts_query, "dra");
+        assert_eq!(app.recall, None);
+        app.recall(false);
+        assert_eq!(app.fts_query, "dra");
+    }
+
+    #[test]
+    fn mouse_selects_toggles_and_resizes() {
+        let parser = crate::note::Parser::new();
+        let notes = parser.parse_file(
+            Path::new("ch01.md"),
+            "<!-- NOTE a #army -->\n<!-- NOTE b #navy -->\n<!-- NOTE c #army -->\n",
+        );
+        let mut app = App::new(
+            NoteStore::from_notes(notes),
+            Analyzer::default(),
+            Formatter::default(),
+            Theme::default(),
+        );
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        let click = |column, row| MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column,
+            row,
+            modifiers: event::KeyModifiers::NONE,
+        };
+
+        // Row 2 is the first list line (status bar, then the border).
+        app.on_mouse(click(2, 3));
+        assert_eq!(app.left_state.selected(), Some(1));
+        assert!(app.selected.contains("navy"));
+        assert_eq!(app.rows().len(), 1);
+
+        app.on_mouse(click(2, 3));
+        assert!(app.selected.is_empty());
+        app.on_mouse(click(40, 3));
+        assert!(app.focus == Focus::Notes);
+        assert_eq!(app.selected_note().unwrap().text, "b #navy");
+        app.on_mouse(click(40, 3));
+        assert!(matches!(app.mode, Mode::Detail { .. }));
+        app.mode = Mode::Browse;
+
+        app.on_mouse(click(app.panes.notes.x, 5));
+        app.on_mouse(MouseEvent {
+            kind: MouseEventKind::Drag(MouseButton::Left),
+            ..click(49, 5)
+        });
+        assert_eq!(app.session.left_width, 50);
+        app.on_mouse(MouseEvent {
+            kind: MouseEventKind::Up(MouseButton::Left),
+            ..click(49, 5)
+        });
+        assert!(app.drag.is_none());
+        app.resize_left(40);
+        assert_eq!(app.session.left_width, PANE_MAX);
+
+        // Border columns elsewhere are ordinary clicks, not drags.
+        app.resize_left(-50);
+        app.show_context = true;
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        let source = app.panes.source.unwrap();
+        app.on_mouse(click(source.x, 4));
+        assert!(app.drag.is_none());
+        assert_eq!(app.note_state.selected(), Some(2));
+        app.on_mouse(click(app.panes.notes.x, 0));
+        assert!(app.drag.is_none());
+        app.on_mouse(click(source.x, source.y + 1));
+        assert!(matches!(app.drag, Some(Border::Source)));
+    }
+
+    #[test]
+    fn sequences_jumps_and_find_move_the_notes_selection() {
+        let source: String = (0..30)
+            .map(|i| match i {
+                3 | 20 => format!("<!-- NOTE the Bridge {i} -->\n"),
+                _ => format!("<!-- NOTE n{i} -->\n"),
+            })
+            .collect();
+        let notes = crate::note::Parser::new().parse_file(Path::new("ch01.md"), &source);
+        let mut app = App::new(
+            NoteStore::from_notes(notes),
+            Analyzer::default(),
+            Formatter::default(),
+            Theme::default(),
+        );
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        let key = |k: &str| k.parse::<Key>().unwrap();
+        app.keymap = Keymap::with_bindings(&[("glossary", &["g"])]).unwrap();
+        app.toggle_focus();
+
+        app.on_key(key("G"));
+        assert_eq!(app.note_state.selected(), Some(29));
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|c| c.symbol())
+            .collect();
+        assert!(screen.contains("30/30"));
+
+        // `g` waits for a second `g`; alone, it runs `glossary` (rebound here
+        // from `t`).
+        assert!(!app.on_key(key("g")));
+        assert_eq!(app.catalog, Catalog::Tags);
+        app.on_key(key("g"));
+        assert_eq!(app.note_state.selected(), Some(0));
+        app.on_key(key("g"));
+        app.flush_pending();
+        assert_eq!(app.catalog, Catalog::Glossary);
+        app.on_action(Action::Glossary);
+        app.focus = Focus::Notes;
+
+        // Pages are the notes pane's 13 rows inside its border.
+        app.on_key(key("ctrl-f"));
+        assert_eq!(app.note_state.selected(), Some(13));
+        app.on_key(key("ctrl-u"));
+        assert_eq!(app.note_state.selected(), Some(7));
+
+        app.on_action(Action::Find);
+        assert!(matches!(app.mode, Mode::Find));
+        app.find = "bridge".into();
+        app.on_find_changed();
+        assert_eq!(app.note_state.selected(), Some(20));
+        app.mode = Mode::Browse;
+        app.on_key(key("n"));
+        assert_eq!(app.note_state.selected(), Some(3));
+        assert_eq!(app.status, "find \"bridge\": 1/2");
+        app.on_key(key("N"));
+        assert_eq!(app.note_state.selected(), Some(20));
+
+        app.find_origin = Some(7);
+        app.find = "nothing".into();
+        app.on_find_changed();
+        assert_eq!(app.note_state.selected(), Some(7));
+    }
+
+    #[test]
+    fn tags_combine_with_and_or_and_exclusions() {
+        let notes = crate::note::Parser::new().parse_file(
+            Path::new("ch01.md"),
+            "<!-- NOTE a #army #navy -->\n<!-- NOTE b #army #draft -->\n\
+             <!-- NOTE c #navy -->\n<!-- NOTE d -->\n",
+        );
+        let mut app = App::new(
+            NoteStore::from_notes(notes),
+            Analyzer::default(),
+            Formatter::default(),
+            Theme::default(),
+        );
+        let texts = |app: &App| -> Vec<String> {
+            app.current_notes().iter().map(|n| n.text.clone()).collect()
+        };
+        // Tags list: army, draft, navy.
+        app.toggle_tag();
+        app.left_state.select(Some(2));
+        app.toggle_tag();
+        assert_eq!(texts(&app), ["a #army #navy"]);
+        app.on_action(Action::TagMode);
+        assert_eq!(texts(&app).len(), 3);
+
+        app.left_state.select(Some(1));
+        app.on_action(Action::ExcludeTag);
+        assert_eq!(texts(&app), ["a #army #navy", "c #navy"]);
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|c| c.symbol())
+            .collect();
+        assert!(screen.contains("[-] draft"));
+        assert!(screen.contains("notes ((#army | #navy) -#draft)"));
+
+        let view = app.current_view();
+        assert!(view.any && view.exclude == ["draft"]);
+        // Space on an excluded tag clears it rather than picking it.
+        app.toggle_tag();
+        assert!(app.excluded.is_empty() && !app.selected.contains("draft"));
+        app.apply_view(&view);
+        assert_eq!(texts(&app).len(), 2);
+    }
+
+    #[test]
+    fn tag_counts_follow_the_selection_and_sort_the_list() {
+        let notes = crate::note::Parser::new().parse_file(
+            Path::new("ch01.md"),
+            "<!-- NOTE a #army #navy -->\n<!-- NOTE b #army -->\n\
+             <!-- NOTE c #army -->\n<!-- NOTE d #draft -->\n",
+        );
+        let mut app = App::new(
+            NoteStore::from_notes(notes),
+            Analyzer::default(),
+            Formatter::default(),
+            Theme::default(),
+        );
+        let counts = &app.tag_counts;
+        assert_eq!((counts["army"], counts["draft"], counts["navy"]), (3, 1, 1));
+
+        // Picking army leaves draft with nothing to add.
+        app.toggle_tag();
+        let counts = &app.tag_counts;
+        assert_eq!(
+            (counts["army"], counts.get("draft"), counts["navy"]),
+            (3, None, 1)
+        );
+        app.on_action(Action::TagMode);
+        let counts = &app.tag_counts;
+        assert_eq!((counts["draft"], counts["navy"]), (4, 3));
+
+        app.on_action(Action::TagMode);
+        app.left_state.select(Some(2));
+        app.on_action(Action::TagSort);
+        assert_eq!(app.filtered_left(), ["army", "navy", "draft"]);
+        assert_eq!(app.selected_left().as_deref(), Some("navy"));
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let row = |y: u16| -> String { (1..20).map(|x| buffer[(x, y)].symbol()).collect() };
+        assert!(row(2).starts_with("[x] army  3"));
+        assert!(row(4).starts_with("[ ] draft  0"));
+        assert!(buffer[(2, 4)].modifier.contains(Modifier::DIM));
+        assert!(!buffer[(2, 2)].modifier.contains(Modifier::DIM));
+    }
+
+    #[test]
+    fn glossary_marks_duplicates_and_compares_definitions() {
+        let notes = crate::note::Parser::new().parse_file(
+            Path::new("ch01.md"),
+            "<!-- NOTE def Knife a short blade -->\n<!-- NOTE def Knife a long blade -->\n\
+             <!-- NOTE def Tide the sea rising -->\n",
+        );
+        let mut app = App::new(
+            NoteStore::from_notes(notes),
+            Analyzer::default(),
+            Formatter::default(),
+            Theme::default(),
+        );
+        app.show_glossary();
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer().clone();
+        let row = |y: u16| -> String { (1..28).map(|x| buffer[(x, y)].symbol()).collect() };
+        assert!(row(2).starts_with("Knife ×2  a short blade"));
+        assert!(row(3).starts_with("Tide  the sea rising"));
+
+        app.left_state.select(Some(1));
+        app.on_action(Action::Compare);
+        assert_eq!(app.status, "Tide has one definition");
+        app.left_state.select(Some(0));
+        app.on_action(Action::Compare);
+        assert!(matches!(app.mode, Mode::Compare { scroll: 0 }));
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let screen: String = buffer.content().iter().map(|c| c.symbol()).collect();
+        assert!(screen.contains("Knife — 2 definitions"));
+        assert!(screen.contains("ch01.md:1") && screen.contains("ch01.md:2"));
+        // Past the overlay title, so not the left pane's gloss.
+        let from = screen.find("definitions").unwrap();
+        let styled = |word: &str| {
+            let at = from + screen[from..].find(word).unwrap();
+            let at = screen[..at].chars().count();
+            buffer.content()[at].fg
+        };
+        assert_eq!(styled("short"), Theme::default().removed.tui().fg.unwrap());
+        assert_eq!(styled("long"), Theme::default().added.tui().fg.unwrap());
+        assert_eq!(styled("blade"), ratatui::style::Color::Reset);
+
+        app.on_action(Action::Back);
+        assert!(matches!(app.mode, Mode::Browse));
+        // A term list left stale by an edit.
+        app.terms.as_mut().unwrap().push("Gone".into());
+        app.left_state.select(Some(2));
+        app.on_action(Action::Compare);
+        assert_eq!(app.status, "Gone has no definitions");
+    }
 }
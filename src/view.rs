@@ -0,0 +1,238 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::configuration;
+use crate::note::Note;
+use crate::search::{Analyzer, FtsIndex};
+use crate::store::{normalize_path, NoteStore};
+
+const FILENAME: &str = "views.json";
+
+/// What the TUI's left pane lists.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Catalog {
+    #[default]
+    Tags,
+    Glossary,
+    Files,
+}
+
+/// A list shown in place of the catalog's notes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverrideKind {
+    Fts,
+    Errata,
+    Pending,
+}
+
+/// A saved TUI selection: `notes view NAME` prints the notes it shows.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct View {
+    #[serde(default)]
+    pub catalog: Catalog,
+    /// Picked tags (tags catalog).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Notes need any picked tag rather than all of them.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub any: bool,
+    /// Tags whose notes are left out.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    /// Selected term or file key (glossary and files catalogs).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub list: Option<OverrideKind>,
+    /// Full-text query, for `list: fts`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub query: String,
+}
+
+impl View {
+    /// The notes the TUI shows for this view.
+    pub fn notes<'a>(
+        &self,
+        store: &'a NoteStore,
+        analyzer: Analyzer,
+    ) -> crate::Result<Vec<&'a Note>> {
+        match self.list {
+            Some(OverrideKind::Fts) => FtsIndex::build(store, analyzer).search(store, &self.query),
+            Some(OverrideKind::Errata) => {
+                Ok(store.notes().iter().filter(|n| n.is_fixme()).collect())
+            }
+            Some(OverrideKind::Pending) => {
+                Ok(store.notes().iter().filter(|n| n.is_todo()).collect())
+            }
+            None => Ok(match (self.catalog, &self.key) {
+                (Catalog::Tags, _) => store.select_tags(&self.tags, self.any, &self.exclude),
+                (Catalog::Glossary, Some(term)) => store.define(term),
+                (Catalog::Files, Some(key)) => store
+                    .notes()
+                    .iter()
+                    .filter(|n| normalize_path(&n.path).starts_with(key.as_str()))
+                    .collect(),
+                (_, None) => Vec::new(),
+            }),
+        }
+    }
+
+    /// One line for view lists, e.g. `#army #navy` or `fts: knives`.
+    pub fn describe(&self) -> String {
+        match self.list {
+            Some(OverrideKind::Fts) => return format!("fts: {}", self.query),
+            Some(OverrideKind::Errata) => return "errata".into(),
+            Some(OverrideKind::Pending) => return "pending".into(),
+            None => {}
+        }
+        match (self.catalog, &self.key) {
+            (Catalog::Tags, _) if self.tags.is_empty() && self.exclude.is_empty() => {
+                "all notes".into()
+            }
+            (Catalog::Tags, _) => tag_expression(&self.tags, self.any, &self.exclude),
+            (Catalog::Glossary, key) => format!("glossary: {}", key.as_deref().unwrap_or("")),
+            (Catalog::Files, key) => format!("files: {}", key.as_deref().unwrap_or("")),
+        }
+    }
+}
+
+/// A tag selection as a query: `#a #b` needs both, `#a | #b` either, and
+/// `-#c` leaves out notes tagged `c`, e.g. `(#a | #b) -#c`. Empty for none.
+pub fn tag_expression(tags: &[String], any: bool, exclude: &[String]) -> String {
+    let picks: Vec<String> = tags.iter().map(|t| format!("#{t}")).collect();
+    let mut parts = match (any, picks.len()) {
+        (true, 2..) if !exclude.is_empty() => vec![format!("({})", picks.join(" | "))],
+        (true, _) => vec![picks.join(" | ")],
+        (false, _) => picks,
+    };
+    parts.retain(|p| !p.is_empty());
+    parts.extend(exclude.iter().map(|t| format!("-#{t}")));
+    parts.join(" ")
+}
+
+/// `views.json` in the config dir: canonical project dir → name → view.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Views {
+    #[serde(default)]
+    directories: HashMap<String, BTreeMap<String, View>>,
+}
+
+impl Views {
+    fn load() -> crate::Result<Self> {
+        match configuration::provider(FILENAME)?.load::<Views>() {
+            Ok(views) => Ok(views),
+            Err(abseil::Error::NotFound) => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Apply `f` to this project's views and store the result.
+    fn update<T>(f: impl FnOnce(&mut BTreeMap<String, View>) -> T) -> crate::Result<T> {
+        let mut views = Self::load()?;
+        let project = views
+            .directories
+            .entry(configuration::project_key()?)
+            .or_default();
+        let result = f(project);
+        if project.is_empty() {
+            views.directories.remove(&configuration::project_key()?);
+        }
+        configuration::provider(FILENAME)?.store(&views)?;
+        Ok(result)
+    }
+}
+
+/// This project's saved views by name.
+pub fn load() -> crate::Result<BTreeMap<String, View>> {
+    let mut views = Views::load()?;
+    Ok(views
+        .directories
+        .remove(&configuration::project_key()?)
+        .unwrap_or_default())
+}
+
+/// Save `view` as `name`, replacing a view of that name.
+pub fn save(name: &str, view: View) -> crate::Result<()> {
+    Views::update(|views| {
+        views.insert(name.to_owned(), view);
+    })
+}
+
+/// Delete the view `name`; false if there was none.
+pub fn remove(name: &str) -> crate::Result<bool> {
+    Views::update(|views| views.remove(name).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::Parser;
+    use std::path::Path;
+
+    fn store() -> NoteStore {
+        let parser = Parser::new();
+        let mut notes = parser.parse_file(
+            Path::new("ch01/a.md"),
+            "<!-- NOTE knives #army #navy -->\n<!-- FIXME dawn #army -->\n",
+        );
+        notes.extend(parser.parse_file(Path::new("ch02.md"), "<!-- TODO ride -->\n"));
+        NoteStore::from_notes(notes)
+    }
+
+    #[test]
+    fn views_select_like_the_tui() {
+        let store = store();
+        let texts = |view: View| -> Vec<String> {
+            view.notes(&store, Analyzer::default())
+                .unwrap()
+                .into_iter()
+                .map(|n| n.text.clone())
+                .collect()
+        };
+        let tags = View {
+            tags: vec!["army".into(), "navy".into()],
+            ..View::default()
+        };
+        assert_eq!(tags.describe(), "#army #navy");
+        assert_eq!(texts(tags.clone()), ["knives #army #navy"]);
+        let either = View {
+            any: true,
+            exclude: vec!["navy".into()],
+            ..tags
+        };
+        assert_eq!(either.describe(), "(#army | #navy) -#navy");
+        assert_eq!(texts(either), ["dawn #army"]);
+        let files = View {
+            catalog: Catalog::Files,
+            key: Some("ch01/".into()),
+            ..View::default()
+        };
+        assert_eq!(texts(files).len(), 2);
+        let pending = View {
+            list: Some(OverrideKind::Pending),
+            ..View::default()
+        };
+        assert_eq!(texts(pending), ["ride"]);
+        let fts = View {
+            list: Some(OverrideKind::Fts),
+            query: "knives".into(),
+            ..View::default()
+        };
+        assert_eq!(fts.describe(), "fts: knives");
+        assert_eq!(texts(fts).len(), 1);
+    }
+
+    #[test]
+    fn serializes_only_what_is_set() {
+        let view = View {
+            list: Some(OverrideKind::Errata),
+            ..View::default()
+        };
+        let json = serde_json::to_string(&view).unwrap();
+        assert_eq!(json, r#"{"catalog":"tags","list":"errata"}"#);
+        assert_eq!(serde_json::from_str::<View>(&json).unwrap(), view);
+    }
+}
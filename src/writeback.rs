@@ -0,0 +1,179 @@
+use std::fs;
+
+use crate::error::Error;
+use crate::note::{self, Note, Parser, Status};
+
+/// A comment rewritten in place: the note as it now reads, and what is
+/// needed to put the old comment back.
+#[derive(Debug)]
+pub struct Edit {
+    pub note: Note,
+    /// The comment body before the edit, for [`restore`].
+    pub previous: String,
+    /// Line breaks added (or removed, negative) by the edit; later notes
+    /// in the file move by this much.
+    pub line_delta: isize,
+}
+
+/// Mark a FIXME or TODO done, won't fix, or open again in its source file.
+pub fn set_status(note: &Note, status: Status) -> crate::Result<Edit> {
+    rewrite(note, |body| note::set_status(body, status))
+}
+
+pub fn add_tag(note: &Note, tag: &str) -> crate::Result<Edit> {
+    set_text(note, &note::add_tag(&note.text, tag))
+}
+
+pub fn remove_tag(note: &Note, tag: &str) -> crate::Result<Edit> {
+    let text = Parser::new()
+        .remove_tag(&note.text, tag)
+        .ok_or_else(|| Error::Message(format!("note has no #{tag}")))?;
+    set_text(note, &text)
+}
+
+/// Replace the note's text (the gloss, for a definition), keeping the
+/// keyword, status marker, and defined term in front of it.
+pub fn set_text(note: &Note, text: &str) -> crate::Result<Edit> {
+    rewrite(note, |body| {
+        let prefix = body.strip_suffix(note.text.as_str())?;
+        Some(format!("{prefix}{text}"))
+    })
+}
+
+/// Put back a comment body saved in [`Edit::previous`].
+pub fn restore(note: &Note, previous: &str) -> crate::Result<Edit> {
+    rewrite(note, |_| Some(previous.to_string()))
+}
+
+/// True when `text` holds a comment delimiter, which would end the note's
+/// comment early (or open another) if written into it.
+pub fn has_delimiter(text: &str) -> bool {
+    text.contains("-->") || text.contains("<!--")
+}
+
+/// Replace the body of the comment `note` came from with `edit(body)`.
+/// Fails instead of writing when the comment no longer matches the note
+/// (the file changed since it was scanned), when the new body holds a
+/// comment delimiter, or when it would not parse as the same kind of note.
+fn rewrite(note: &Note, edit: impl FnOnce(&str) -> Option<String>) -> crate::Result<Edit> {
+    let parser = Parser::new();
+    let mut source = fs::read_to_string(&note.path)?;
+    let range = parser.locate(&source, note).ok_or_else(|| {
+        Error::Message(format!(
+            "{}:{} changed on disk; restart to rescan",
+            note.path.display(),
+            note.line
+        ))
+    })?;
+    let previous = source[range.clone()].to_string();
+    let body = edit(&previous)
+        .ok_or_else(|| Error::Message("this note cannot be edited that way".into()))?;
+    if has_delimiter(&body) {
+        return Err(Error::Message("notes cannot contain <!-- or -->".into()));
+    }
+    let updated = parser
+        .parse_body(note.path.clone(), note.line, &body)
+        .filter(|updated| updated.kind == note.kind)
+        .ok_or_else(|| Error::Message("the edit would leave no note of this kind".into()))?;
+    let line_delta = newlines(&body) - newlines(&previous);
+    source.replace_range(range, &body);
+    fs::write(&note.path, source)?;
+    Ok(Edit {
+        note: updated,
+        previous,
+        line_delta,
+    })
+}
+
+fn newlines(s: &str) -> isize {
+    s.matches('\n').count() as isize
+}
+
+/// A file in the temp dir for write-back tests, deleted when dropped (so
+/// also when a test fails).
+#[cfg(test)]
+pub(crate) struct Scratch(pub std::path::PathBuf);
+
+#[cfg(test)]
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Write `source` to a [`Scratch`] file named after `name` and parse it.
+#[cfg(test)]
+pub(crate) fn scratch(name: &str, source: &str) -> (Scratch, Vec<Note>) {
+    let path = std::env::temp_dir().join(format!("notes-scratch-{}-{name}", std::process::id()));
+    fs::write(&path, source).unwrap();
+    let notes = Parser::new().parse_file(&path, source);
+    (Scratch(path), notes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggles_status_in_place() {
+        let (file, notes) = scratch("status.md", "Intro.\n<!-- TODO [ ] map #draft --> tail\n");
+        let path = &file.0;
+
+        let done = set_status(&notes[0], Status::Done).unwrap();
+        assert_eq!(done.note.status, Status::Done);
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            "Intro.\n<!-- TODO [x] map #draft --> tail\n"
+        );
+
+        // `notes[0]` is stale now: the file no longer has an open TODO there.
+        assert!(set_status(&notes[0], Status::Done).is_err());
+        set_status(&done.note, Status::Open).unwrap();
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            "Intro.\n<!-- TODO [ ] map #draft --> tail\n"
+        );
+    }
+
+    #[test]
+    fn edits_tags_and_text_then_restores() {
+        let (file, notes) = scratch(
+            "tags.md",
+            "<!-- NOTE a tenth\nof the crop. #tax -->\n<!-- NOTE def tithe a tax. -->\n",
+        );
+        let path = &file.0;
+
+        let tagged = add_tag(&notes[0], "army").unwrap();
+        assert_eq!(tagged.note.tags, ["tax", "army"]);
+        let untagged = remove_tag(&tagged.note, "tax").unwrap();
+        assert_eq!(untagged.note.text, "a tenth\nof the crop. #army");
+        assert!(remove_tag(&untagged.note, "tax").is_err());
+
+        let edited = set_text(&untagged.note, "a tithe. #army").unwrap();
+        assert_eq!(edited.line_delta, -1);
+        let gloss = Note {
+            line: notes[1].line.saturating_add_signed(edited.line_delta),
+            ..notes[1].clone()
+        };
+        set_text(&gloss, "a tenth.").unwrap();
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            "<!-- NOTE a tithe. #army -->\n<!-- NOTE def tithe a tenth. -->\n"
+        );
+        assert!(set_text(&edited.note, "").is_err());
+
+        let undone = restore(&edited.note, &edited.previous).unwrap();
+        assert_eq!(undone.note, untagged.note);
+        assert_eq!(undone.line_delta, 1);
+    }
+
+    #[test]
+    fn rejects_comment_delimiters() {
+        let source = "<!-- NOTE the bridge #plot -->\n";
+        let (file, notes) = scratch("delimiters.md", source);
+        assert!(set_text(&notes[0], "fix the bridge --> later").is_err());
+        assert!(set_text(&notes[0], "a <!-- nested one").is_err());
+        assert!(add_tag(&notes[0], "x-->").is_err());
+        assert_eq!(fs::read_to_string(&file.0).unwrap(), source);
+    }
+}